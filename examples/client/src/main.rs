@@ -2,10 +2,13 @@ use std::path::PathBuf;
 
 use clap::Parser;
 use nsm_nitro_enclave_utils::{
-    api::{nsm::AttestationDoc, ByteBuf},
+    api::{nsm::AttestationDoc, DisplayAttestationDoc, Nonce},
     time::Time,
     verify::AttestationDocVerifierExt,
 };
+use nsm_nitro_enclave_utils_keygen::{
+    fingerprint_sha384, Certificate, DerDecodeExt, DerEncodeExt, NsmCertChain,
+};
 use reqwest::StatusCode;
 use serde::Deserialize;
 
@@ -17,6 +20,9 @@ struct Args {
         default_value = "./test_data/root-certificate.der"
     )]
     root_cert: PathBuf,
+    /// The JSON printed by `nsm-nitro-enclave-utils-keygen`, in any format. Its root certificate replaces `--root-cert`.
+    #[arg(long, conflicts_with = "root_cert")]
+    cert_chain: Option<PathBuf>,
 }
 
 #[derive(Deserialize)]
@@ -27,11 +33,25 @@ struct AttestResponse {
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
-    let root_cert = std::fs::read(&args.root_cert).unwrap();
+    let root_cert = match args.cert_chain {
+        Some(path) => {
+            let json = std::fs::read_to_string(&path).unwrap();
+            NsmCertChain::from_json(&json)
+                .expect("Failed to parse the certificate chain")
+                .root_signer
+                .cert
+        }
+        None => Certificate::from_der(&std::fs::read(&args.root_cert).unwrap()).unwrap(),
+    };
+    println!(
+        "Verifying against root certificate {}",
+        fingerprint_sha384(&root_cert)
+    );
+    let root_cert = root_cert.to_der().unwrap();
 
-    let nonce = hex::encode(ByteBuf::from(&[0u8; 32]).as_ref());
+    let nonce = Nonce::new(vec![0u8; 32]).unwrap();
 
-    let response = reqwest::get(format!("http://127.0.0.1:3000/attest/{}", nonce))
+    let response = reqwest::get(format!("http://127.0.0.1:3000/attest/{}", nonce.to_hex()))
         .await
         .unwrap();
 
@@ -43,22 +63,19 @@ async fn main() {
     let response_body = response.text().await.unwrap();
     let response: AttestResponse = serde_json::from_str(response_body.as_ref()).unwrap();
 
-    let doc = AttestationDoc::from_cose(&response.document, &root_cert, Time::default()).unwrap();
-
-    // Ensure our nonce made it into our document and convert to a string so we can compare it to our hex string
-    let Some(doc_nonce) = doc
-        .nonce
-        .clone()
-        .map(|nonce| String::from_utf8(nonce.to_vec()).unwrap())
-    else {
-        eprintln!("Document nonce is missing!");
-        return;
+    // Ensure our nonce made it into our document. The server decodes the hex string, including the nonce's bytes.
+    let doc = match AttestationDoc::from_cose_with_nonce(
+        &response.document,
+        &root_cert,
+        Time::default(),
+        nonce.as_bytes(),
+    ) {
+        Ok(doc) => doc,
+        Err(err) => {
+            eprintln!("Failed to verify attestation document: {:?}", err.kind());
+            return;
+        }
     };
 
-    if nonce != doc_nonce {
-        eprintln!("Nonce mismatch! Expected {}, received {}", nonce, doc_nonce);
-        return;
-    }
-
-    println!("Success! {:?}", doc);
+    println!("Success! {}", DisplayAttestationDoc(doc));
 }
@@ -5,24 +5,27 @@ use axum::{
     routing::get,
     Router,
 };
+#[cfg(feature = "dev")]
+use nsm_nitro_enclave_utils::{api::ByteBuf, pcr::Pcrs};
 use nsm_nitro_enclave_utils::{
     api::{
         nsm::{Request as NsmRequest, Response as NsmResponse},
-        ByteBuf,
+        Nonce,
     },
     driver::Driver,
 };
-#[cfg(feature = "dev")]
-use nsm_nitro_enclave_utils::{
-    api::{DecodePrivateKey, SecretKey},
-    pcr::Pcrs,
-};
 use serde::Serialize;
 use std::sync::Arc;
 
+#[cfg(feature = "vsock")]
+mod vsock;
+
 #[cfg(feature = "dev")]
 #[derive(clap::Parser, Debug)]
 struct Args {
+    /// The JSON printed by `nsm-nitro-enclave-utils-keygen`, in any format. Replaces the individual certificate and signing key files.
+    #[arg(long, conflicts_with_all = ["signing_key", "end_cert", "int_certs"])]
+    cert_chain: Option<std::path::PathBuf>,
     #[arg(
         long,
         allow_hyphen_values = true,
@@ -57,32 +60,43 @@ async fn main() {
         use clap::Parser;
         let args = Args::parse();
 
-        let int_certs = args
-            .int_certs
-            .into_iter()
-            .map(|path| {
-                let der = std::fs::read(&path).unwrap();
-                ByteBuf::from(der)
-            })
-            .collect::<Vec<ByteBuf>>();
-
-        let end_cert = {
-            let der = std::fs::read(&args.end_cert).unwrap();
-            ByteBuf::from(der)
+        let builder = match args.cert_chain {
+            Some(path) => {
+                let json = std::fs::read_to_string(&path).unwrap();
+                nsm_nitro_enclave_utils_keygen::NsmCertChain::from_json(&json)
+                    .expect("Failed to parse the certificate chain")
+                    .dev_nitro_builder()
+                    .expect("The end signing key must be P-384")
+            }
+            None => {
+                let int_certs = args
+                    .int_certs
+                    .into_iter()
+                    .map(|path| {
+                        let der = std::fs::read(&path).unwrap();
+                        ByteBuf::from(der)
+                    })
+                    .collect::<Vec<ByteBuf>>();
+
+                let end_cert = std::fs::read(&args.end_cert).unwrap();
+                let signing_key = std::fs::read(&args.signing_key).unwrap();
+
+                nsm_nitro_enclave_utils::driver::dev::DevNitroBuilder::try_from_der(
+                    &signing_key,
+                    &end_cert,
+                )
+                .expect("Failed to parse the signing key or end certificate")
+                .ca_bundle(int_certs)
+            }
         };
 
-        let signing_key = {
-            let der = std::fs::read(&args.signing_key).unwrap();
-            SecretKey::from_pkcs8_der(&der).unwrap()
-        };
-
-        nsm_nitro_enclave_utils::driver::dev::DevNitro::builder(signing_key, end_cert)
+        builder
             // Using `Pcrs::zeros` to get attestation documents similar to how the Nsm module will return all zeros in "debug mode"
             // https://docs.aws.amazon.com/enclaves/latest/user/getting-started.html#run
             // `Pcrs` can be generated in another ways too, but some of them require extra feature flags not enabled in this binary.
             .pcrs(Pcrs::zeros())
-            .ca_bundle(int_certs)
-            .build()
+            .try_build()
+            .expect("The end certificate must contain the signing key's public key")
     };
 
     #[cfg(not(feature = "dev"))]
@@ -96,12 +110,20 @@ async fn main() {
         .route("/attest/:nonce", get(attest))
         .with_state(app_state);
 
-    // Configure this to listen on vsock inside Nitro environments
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000")
-        .await
-        .unwrap();
+    // Inside Nitro environments, the `vsock` feature must be enabled, listening on vsock rather than tcp
+    #[cfg(feature = "vsock")]
+    {
+        let listener = vsock::VsockListener::bind(vsock::VMADDR_CID_ANY, 3000).unwrap();
+        vsock::serve(listener, app).await.unwrap();
+    }
 
-    axum::serve(listener, app).await.unwrap();
+    #[cfg(not(feature = "vsock"))]
+    {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:3000")
+            .await
+            .unwrap();
+        axum::serve(listener, app).await.unwrap();
+    }
 }
 
 #[derive(Serialize)]
@@ -109,11 +131,15 @@ struct AttestResponse {
     document: Vec<u8>,
 }
 
-async fn attest(State(app_state): State<AppState>, Path(nonce): Path<ByteBuf>) -> Response {
+async fn attest(State(app_state): State<AppState>, Path(nonce): Path<String>) -> Response {
+    let Ok(nonce) = Nonce::from_hex(&nonce) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+
     let response = app_state.nitro.process_request(NsmRequest::Attestation {
         user_data: None,
         public_key: None,
-        nonce: Some(nonce),
+        nonce: Some(nonce.into()),
     });
 
     if let NsmResponse::Attestation { document } = response {
@@ -0,0 +1,177 @@
+//! Serves the app over vsock, the only network interface available inside a Nitro Enclave.
+//! The enclave's parent instance forwards traffic to the listener via `vsock-proxy`, or its own vsock client.
+
+use axum::Router;
+use std::io;
+
+/// Accepts connections from any CID
+pub const VMADDR_CID_ANY: u32 = u32::MAX;
+
+/// A vsock listener, registered with the tokio runtime
+pub struct VsockListener {
+    #[cfg(target_os = "linux")]
+    socket: tokio::io::unix::AsyncFd<socket2::Socket>,
+    #[cfg(not(target_os = "linux"))]
+    never: std::convert::Infallible,
+}
+
+impl VsockListener {
+    /// Binds a listener to `cid` and `port`. Use [`VMADDR_CID_ANY`] to accept connections from the parent instance.
+    /// Fails with [`io::ErrorKind::Unsupported`] on targets other than Linux, which don't support vsock.
+    #[cfg(target_os = "linux")]
+    pub fn bind(cid: u32, port: u32) -> io::Result<Self> {
+        use socket2::{Domain, SockAddr, Socket, Type};
+
+        let socket = Socket::new(Domain::VSOCK, Type::STREAM, None)?;
+        socket.set_nonblocking(true)?;
+        socket.bind(&SockAddr::vsock(cid, port))?;
+        socket.listen(128)?;
+
+        Ok(Self {
+            socket: tokio::io::unix::AsyncFd::new(socket)?,
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn bind(_cid: u32, _port: u32) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "vsock is only supported on Linux",
+        ))
+    }
+
+    /// Accepts the next connection, along with the peer's `(cid, port)`
+    #[cfg(target_os = "linux")]
+    pub async fn accept(&self) -> io::Result<(VsockStream, (u32, u32))> {
+        loop {
+            let mut guard = self.socket.readable().await?;
+            if let Ok(accepted) = guard.try_io(|socket| socket.get_ref().accept()) {
+                let (socket, addr) = accepted?;
+                let peer_addr = addr.as_vsock_address().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, "peer isn't a vsock address")
+                })?;
+                return Ok((VsockStream::new(socket)?, peer_addr));
+            }
+        }
+    }
+}
+
+/// A vsock connection, registered with the tokio runtime
+#[cfg(target_os = "linux")]
+pub struct VsockStream {
+    socket: tokio::io::unix::AsyncFd<socket2::Socket>,
+}
+
+#[cfg(target_os = "linux")]
+impl VsockStream {
+    fn new(socket: socket2::Socket) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket: tokio::io::unix::AsyncFd::new(socket)?,
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl tokio::io::AsyncRead for VsockStream {
+    fn poll_read(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        use std::io::Read;
+
+        loop {
+            let mut guard = std::task::ready!(self.socket.poll_read_ready(cx))?;
+            let unfilled = buf.initialize_unfilled();
+            match guard.try_io(|socket| socket.get_ref().read(unfilled)) {
+                Ok(read) => {
+                    buf.advance(read?);
+                    return std::task::Poll::Ready(Ok(()));
+                }
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl tokio::io::AsyncWrite for VsockStream {
+    fn poll_write(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<io::Result<usize>> {
+        use std::io::Write;
+
+        loop {
+            let mut guard = std::task::ready!(self.socket.poll_write_ready(cx))?;
+            match guard.try_io(|socket| socket.get_ref().write(buf)) {
+                Ok(written) => return std::task::Poll::Ready(written),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        std::task::Poll::Ready(self.socket.get_ref().shutdown(std::net::Shutdown::Write))
+    }
+}
+
+/// Serves `app` on `listener`, mirroring [`axum::serve`], which only supports tcp listeners.
+/// Like [`axum::serve`], a connection that fails to be accepted is logged and skipped rather than stopping the server.
+#[cfg(target_os = "linux")]
+pub async fn serve(listener: VsockListener, app: Router) -> io::Result<()> {
+    use hyper_util::{rt::TokioIo, service::TowerToHyperService};
+
+    loop {
+        let (stream, (cid, port)) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(err) => {
+                handle_accept_error(err).await;
+                continue;
+            }
+        };
+        let service = TowerToHyperService::new(app.clone());
+        tokio::spawn(async move {
+            if let Err(err) = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                eprintln!("Failed to serve vsock connection from {cid}:{port}: {err}");
+            }
+        });
+    }
+}
+
+/// Mirrors [`axum::serve`]: errors caused by the peer only affect its connection,
+/// others, i.e. running out of file descriptors, are retried after a delay instead of in a busy loop
+#[cfg(target_os = "linux")]
+async fn handle_accept_error(err: io::Error) {
+    if matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+    ) {
+        return;
+    }
+
+    eprintln!("Failed to accept vsock connection: {err}");
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+}
+
+#[cfg(not(target_os = "linux"))]
+pub async fn serve(listener: VsockListener, _app: Router) -> io::Result<()> {
+    match listener.never {}
+}
@@ -0,0 +1,725 @@
+//! Provides [`NsmCertChainBuilder`], which allows every parameter of an [`NsmCertChain`] to be configured before it's generated.
+
+use p384::ecdsa::signature::{Keypair, Signer};
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use x509_cert::{
+    builder::{Builder, CertificateBuilder, Profile},
+    crl::{CertificateList, RevokedCert, TbsCertList},
+    der::{
+        asn1::{ObjectIdentifier, OctetString, Uint},
+        oid::AssociatedOid,
+        Encode,
+    },
+    ext::{pkix::CrlNumber, AsExtension, Extension},
+    spki::{DynSignatureAlgorithmIdentifier, EncodePublicKey, SignatureBitStringEncoding},
+    time::{Time, Validity},
+    Version,
+};
+
+use crate::{
+    Certificate, CertificateIssuer, CertificateSigner, Error, ErrorContext, ErrorKind,
+    KeyAlgorithm, Name, NsmCertChain, SerialNumber, SigningKey, DEFAULT_END_SUBJECT,
+    DEFAULT_INT_SUBJECT, DEFAULT_ROOT_SUBJECT,
+};
+
+/// A builder for [`NsmCertChain`]
+pub struct NsmCertChainBuilder {
+    valid_until: Duration,
+    not_before: Option<SystemTime>,
+    key_algorithm: KeyAlgorithm,
+    intermediate_count: usize,
+    root_subject: Name,
+    int_subject: Name,
+    end_subject: Name,
+    root_serial: Option<SerialNumber>,
+    int_serial: Option<SerialNumber>,
+    end_serial: Option<SerialNumber>,
+    seed: Option<[u8; 32]>,
+    end_extensions: Vec<Extension>,
+}
+
+impl NsmCertChainBuilder {
+    /// `valid_until`: how long the generated certificates will be valid for, starting at their `notBefore`. Must be non-zero.
+    pub(crate) fn new(valid_until: Duration) -> Self {
+        Self {
+            valid_until,
+            not_before: None,
+            key_algorithm: KeyAlgorithm::default(),
+            intermediate_count: 1,
+            root_subject: Name::from_str(DEFAULT_ROOT_SUBJECT).expect("Valid root subject"),
+            int_subject: Name::from_str(DEFAULT_INT_SUBJECT).expect("Valid int subject"),
+            end_subject: Name::from_str(DEFAULT_END_SUBJECT).expect("Valid end subject"),
+            root_serial: None,
+            int_serial: None,
+            end_serial: None,
+            seed: None,
+            end_extensions: Vec::new(),
+        }
+    }
+
+    /// Set the moment every certificate in the chain becomes valid. Defaults to now.
+    /// The certificates remain valid for the `valid_until` duration provided to [`NsmCertChain::builder`], starting at `not_before`.
+    ///
+    /// Setting this in the past or future produces chains that are useful for testing time-sensitive verification,
+    /// i.e. a chain that isn't valid yet, or one that is valid from an hour ago until an hour from now.
+    pub fn not_before(self, not_before: SystemTime) -> Self {
+        Self {
+            not_before: Some(not_before),
+            ..self
+        }
+    }
+
+    /// Set the algorithm used to generate every key in the chain
+    pub fn key_algorithm(self, key_algorithm: KeyAlgorithm) -> Self {
+        Self {
+            key_algorithm,
+            ..self
+        }
+    }
+
+    /// Set the amount of intermediate certificates between the root and end certificate. Defaults to 1, must be non-zero.
+    ///
+    /// The first intermediate is signed by the root, each following intermediate is signed by the one before it, and the last one signs the end certificate.
+    pub fn intermediate_count(self, intermediate_count: usize) -> Self {
+        Self {
+            intermediate_count,
+            ..self
+        }
+    }
+
+    /// Set the root certificate's subject
+    pub fn root_subject(self, root_subject: Name) -> Self {
+        Self {
+            root_subject,
+            ..self
+        }
+    }
+
+    /// Set the intermediate certificate's subject. The intermediate's issuer is always the root's subject.
+    ///
+    /// When more than one intermediate is generated, this subject is used by the intermediate that signs the end certificate.
+    /// Every other intermediate extends it with an `OU=int-{n}` attribute, where `n` counts up from 1 starting at the intermediate signed by the root.
+    pub fn int_subject(self, int_subject: Name) -> Self {
+        Self {
+            int_subject,
+            ..self
+        }
+    }
+
+    /// Set the end certificate's subject. The end certificate's issuer is always the intermediate's subject.
+    pub fn end_subject(self, end_subject: Name) -> Self {
+        Self {
+            end_subject,
+            ..self
+        }
+    }
+
+    /// Set the root certificate's serial number. If omitted, a random serial number is generated.
+    pub fn root_serial(self, root_serial: SerialNumber) -> Self {
+        Self {
+            root_serial: Some(root_serial),
+            ..self
+        }
+    }
+
+    /// Set the intermediate certificate's serial number. If omitted, a random serial number is generated.
+    ///
+    /// When more than one intermediate is generated, this serial number is used by the intermediate that signs the end certificate.
+    pub fn int_serial(self, int_serial: SerialNumber) -> Self {
+        Self {
+            int_serial: Some(int_serial),
+            ..self
+        }
+    }
+
+    /// Set the end certificate's serial number. If omitted, a random serial number is generated.
+    pub fn end_serial(self, end_serial: SerialNumber) -> Self {
+        Self {
+            end_serial: Some(end_serial),
+            ..self
+        }
+    }
+
+    /// Derive every signing key and random serial number from `seed`, rather than a random source.
+    /// Chains built with the same seed and configuration are byte-identical, as ECDSA signatures are deterministic.
+    /// The validity starts now unless [`NsmCertChainBuilder::not_before`] is also set, so it should be set for full reproducibility.
+    ///
+    /// The seed determines the private keys, so seeded chains must only be used for testing.
+    pub fn seed(self, seed: [u8; 32]) -> Self {
+        Self {
+            seed: Some(seed),
+            ..self
+        }
+    }
+
+    /// Add `end_extensions` to the end certificate, i.e. an extended key usage or a custom OID. Defaults to none.
+    /// The end certificate always includes the basic constraints, key usage, and key identifier extensions.
+    /// Building fails with [`ErrorKind::Certificate`] if an extension duplicates one of them, or another provided extension.
+    pub fn end_extensions(self, end_extensions: Vec<Extension>) -> Self {
+        Self {
+            end_extensions,
+            ..self
+        }
+    }
+
+    /// Generates a new [`NsmCertChain`]
+    pub fn build(self) -> Result<NsmCertChain, Error> {
+        if self.intermediate_count == 0 {
+            return Err(Error::new(
+                ErrorKind::IntermediateCount,
+                ErrorContext("At least one intermediate certificate is required"),
+            ));
+        }
+
+        let validity = validity(
+            self.not_before.unwrap_or_else(SystemTime::now),
+            self.valid_until,
+        )?;
+
+        let mut rng = match self.seed {
+            Some(seed) => ChaCha20Rng::from_seed(seed),
+            None => ChaCha20Rng::from_rng(rand::thread_rng())
+                .map_err(|err| Error::new(ErrorKind::Random, err))?,
+        };
+
+        let root_signing_key = SigningKey::generate_with_rng(self.key_algorithm, &mut rng);
+        let root_cert = build_cert(
+            Profile::Root,
+            serial_or_random(self.root_serial, &mut rng)?,
+            validity,
+            self.root_subject.clone(),
+            &root_signing_key,
+            &root_signing_key,
+            &[],
+        )?;
+
+        let mut int_signers: Vec<CertificateSigner> = Vec::with_capacity(self.intermediate_count);
+        for n in 1..=self.intermediate_count {
+            let is_last = n == self.intermediate_count;
+            let (issuer, issuer_key) = match int_signers.last() {
+                Some(signer) => (
+                    signer.cert.tbs_certificate.subject.clone(),
+                    &signer.signing_key,
+                ),
+                None => (self.root_subject.clone(), &root_signing_key),
+            };
+            let (subject, serial) = if is_last {
+                (self.int_subject.clone(), self.int_serial.clone())
+            } else {
+                let subject = Name::from_str(&format!("OU=int-{n},{}", self.int_subject))
+                    .map_err(|err| Error::new(ErrorKind::Certificate, err))?;
+                (subject, None)
+            };
+
+            let signing_key = SigningKey::generate_with_rng(self.key_algorithm, &mut rng);
+            let cert = build_cert(
+                Profile::SubCA {
+                    issuer,
+                    path_len_constraint: None,
+                },
+                serial_or_random(serial, &mut rng)?,
+                validity,
+                subject,
+                &signing_key,
+                issuer_key,
+                &[],
+            )?;
+            int_signers.push(CertificateSigner { cert, signing_key });
+        }
+        // Guaranteed by the `intermediate_count` check above
+        let int_signer = int_signers.last().expect("At least one intermediate");
+
+        let end_signer = issue_leaf(
+            &int_signer.cert,
+            &int_signer.signing_key,
+            self.end_subject,
+            self.end_serial,
+            validity,
+            &self.end_extensions,
+            &mut rng,
+        )?;
+
+        Ok(NsmCertChain {
+            root_signer: CertificateIssuer {
+                cert: root_cert,
+                signing_key: Some(root_signing_key),
+            },
+            int_signers: int_signers
+                .into_iter()
+                .map(CertificateIssuer::from)
+                .collect(),
+            end_signer,
+        })
+    }
+}
+
+/// Computes the validity of a certificate that is valid for `valid_until`, starting at `not_before`.
+pub(crate) fn validity(not_before: SystemTime, valid_until: Duration) -> Result<Validity, Error> {
+    if valid_until.is_zero() {
+        return Err(Error::new(
+            ErrorKind::Validity,
+            ErrorContext("Certificates must be valid for a non-zero duration"),
+        ));
+    }
+
+    Ok(Validity {
+        not_before: Time::try_from(not_before)
+            .map_err(|err| Error::new(ErrorKind::Validity, err))?,
+        not_after: Time::try_from(not_before + valid_until)
+            .map_err(|err| Error::new(ErrorKind::Validity, err))?,
+    })
+}
+
+/// Generates a new end certificate and signing key, signed by `issuer_key` on behalf of `issuer`.
+/// The new signing key uses the same [`KeyAlgorithm`] as the issuer.
+/// The certificate doesn't include an extended key usage extension, which verifiers checking for `serverAuth` only if present accept.
+pub(crate) fn issue_leaf<R>(
+    issuer: &Certificate,
+    issuer_key: &SigningKey,
+    subject: Name,
+    serial: Option<SerialNumber>,
+    validity: Validity,
+    extensions: &[Extension],
+    rng: &mut R,
+) -> Result<CertificateSigner, Error>
+where
+    R: RngCore + CryptoRng,
+{
+    let signing_key = SigningKey::generate_with_rng(issuer_key.key_algorithm(), rng);
+    let cert = build_cert(
+        Profile::Leaf {
+            issuer: issuer.tbs_certificate.subject.clone(),
+            enable_key_agreement: false,
+            enable_key_encipherment: false,
+        },
+        serial_or_random(serial, rng)?,
+        validity,
+        subject,
+        &signing_key,
+        issuer_key,
+        extensions,
+    )?;
+
+    Ok(CertificateSigner { cert, signing_key })
+}
+
+/// Builds a certificate for `subject_key`'s public key, signed by `issuer_key`, including `extensions` along with those added by `profile`.
+/// The certificate's signature algorithm is selected by the curve of `issuer_key`.
+fn build_cert(
+    profile: Profile,
+    serial_number: SerialNumber,
+    validity: Validity,
+    subject: Name,
+    subject_key: &SigningKey,
+    issuer_key: &SigningKey,
+    extensions: &[Extension],
+) -> Result<Certificate, Error> {
+    let spki = subject_key
+        .public_key_info()
+        .map_err(|err| Error::new(ErrorKind::PublicKey, err))?;
+
+    match issuer_key {
+        SigningKey::P256(issuer_key) => sign_cert::<_, p256::ecdsa::DerSignature>(
+            profile,
+            serial_number,
+            validity,
+            subject,
+            spki,
+            issuer_key,
+            extensions,
+        ),
+        SigningKey::P384(issuer_key) => sign_cert::<_, p384::ecdsa::DerSignature>(
+            profile,
+            serial_number,
+            validity,
+            subject,
+            spki,
+            issuer_key,
+            extensions,
+        ),
+    }
+}
+
+fn sign_cert<S, Signature>(
+    profile: Profile,
+    serial_number: SerialNumber,
+    validity: Validity,
+    subject: Name,
+    spki: x509_cert::spki::SubjectPublicKeyInfoOwned,
+    issuer_key: &S,
+    extensions: &[Extension],
+) -> Result<Certificate, Error>
+where
+    S: Keypair + DynSignatureAlgorithmIdentifier + Signer<Signature>,
+    S::VerifyingKey: EncodePublicKey,
+    Signature: SignatureBitStringEncoding,
+{
+    let mut builder =
+        CertificateBuilder::new(profile, serial_number, validity, subject, spki, issuer_key)
+            .map_err(|err| Error::new(ErrorKind::Certificate, err))?;
+    for extension in extensions {
+        builder
+            .add_extension(&PrebuiltExtension(extension))
+            .map_err(|err| Error::new(ErrorKind::Certificate, err))?;
+    }
+    let cert = builder
+        .build::<Signature>()
+        .map_err(|err| Error::new(ErrorKind::Certificate, err))?;
+
+    let mut extension_ids = BTreeSet::new();
+    if !cert
+        .tbs_certificate
+        .extensions
+        .iter()
+        .flatten()
+        .all(|extension| extension_ids.insert(extension.extn_id))
+    {
+        return Err(Error::new(
+            ErrorKind::Certificate,
+            ErrorContext("A certificate can't include the same extension more than once"),
+        ));
+    }
+
+    Ok(cert)
+}
+
+/// Issues a CRL signed by `issuer_key` on behalf of `issuer`, revoking the certificates with the `revoked` serial numbers as of `validity.not_before`.
+/// The CRL's `nextUpdate` is `validity.not_after`. The signature algorithm is selected by the curve of `issuer_key`.
+pub(crate) fn issue_crl(
+    issuer: &Certificate,
+    issuer_key: &SigningKey,
+    revoked: &[SerialNumber],
+    validity: Validity,
+) -> Result<CertificateList, Error> {
+    match issuer_key {
+        SigningKey::P256(issuer_key) => {
+            sign_crl::<_, p256::ecdsa::DerSignature>(issuer, revoked, validity, issuer_key)
+        }
+        SigningKey::P384(issuer_key) => {
+            sign_crl::<_, p384::ecdsa::DerSignature>(issuer, revoked, validity, issuer_key)
+        }
+    }
+}
+
+fn sign_crl<S, Signature>(
+    issuer: &Certificate,
+    revoked: &[SerialNumber],
+    validity: Validity,
+    issuer_key: &S,
+) -> Result<CertificateList, Error>
+where
+    S: DynSignatureAlgorithmIdentifier + Signer<Signature>,
+    Signature: SignatureBitStringEncoding,
+{
+    // RFC 5280 requires every CRL to include a CRL number
+    let crl_number = Uint::new(&[1])
+        .and_then(|number| CrlNumber(number).to_der())
+        .and_then(OctetString::new)
+        .map_err(|err| Error::new(ErrorKind::Crl, err))?;
+
+    let tbs_cert_list = TbsCertList {
+        version: Version::V2,
+        signature: issuer_key
+            .signature_algorithm_identifier()
+            .map_err(|err| Error::new(ErrorKind::Crl, err))?,
+        issuer: issuer.tbs_certificate.subject.clone(),
+        this_update: validity.not_before,
+        next_update: Some(validity.not_after),
+        revoked_certificates: Some(
+            revoked
+                .iter()
+                .map(|serial_number| RevokedCert {
+                    serial_number: serial_number.clone(),
+                    revocation_date: validity.not_before,
+                    crl_entry_extensions: None,
+                })
+                .collect(),
+        ),
+        crl_extensions: Some(vec![Extension {
+            extn_id: CrlNumber::OID,
+            critical: false,
+            extn_value: crl_number,
+        }]),
+    };
+
+    let tbs_der = tbs_cert_list
+        .to_der()
+        .map_err(|err| Error::new(ErrorKind::Crl, err))?;
+    let signature = issuer_key
+        .try_sign(&tbs_der)
+        .map_err(|err| Error::new(ErrorKind::Crl, err))?
+        .to_bitstring()
+        .map_err(|err| Error::new(ErrorKind::Crl, err))?;
+
+    Ok(CertificateList {
+        signature_algorithm: tbs_cert_list.signature.clone(),
+        tbs_cert_list,
+        signature,
+    })
+}
+
+/// Allows an already encoded [`Extension`] to be added by [`CertificateBuilder::add_extension`], which only accepts extensions with a static OID.
+/// [`AsExtension::to_extension`] returns the wrapped extension as-is, its OID and encoding aren't used.
+struct PrebuiltExtension<'a>(&'a Extension);
+
+impl AssociatedOid for PrebuiltExtension<'_> {
+    // Unused, `to_extension` provides the wrapped extension's OID
+    const OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("0.0");
+}
+
+impl Encode for PrebuiltExtension<'_> {
+    fn encoded_len(&self) -> x509_cert::der::Result<x509_cert::der::Length> {
+        self.0.extn_value.encoded_len()
+    }
+
+    fn encode(&self, encoder: &mut impl x509_cert::der::Writer) -> x509_cert::der::Result<()> {
+        self.0.extn_value.encode(encoder)
+    }
+}
+
+impl AsExtension for PrebuiltExtension<'_> {
+    fn critical(&self, _subject: &Name, _extensions: &[Extension]) -> bool {
+        self.0.critical
+    }
+
+    fn to_extension(
+        &self,
+        _subject: &Name,
+        _extensions: &[Extension],
+    ) -> Result<Extension, x509_cert::der::Error> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Length of randomly generated serial numbers, in bytes.
+/// RFC 5280 requires serial numbers to be positive and no longer than 20 bytes.
+const SERIAL_NUMBER_LENGTH: usize = 16;
+
+fn serial_or_random<R>(serial: Option<SerialNumber>, rng: &mut R) -> Result<SerialNumber, Error>
+where
+    R: RngCore,
+{
+    if let Some(serial) = serial {
+        return Ok(serial);
+    }
+
+    let mut bytes = [0u8; SERIAL_NUMBER_LENGTH];
+    rng.fill_bytes(&mut bytes);
+    // Keep the most significant bit clear and the next one set, ensuring the serial is positive, non-zero, and always uses the full length.
+    bytes[0] = (bytes[0] & 0x7f) | 0x40;
+
+    SerialNumber::new(&bytes).map_err(|err| Error::new(ErrorKind::SerialNumber, err))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        DerEncodeExt, ErrorKind, Extension, KeyAlgorithm, NsmCertChain, SerialNumber,
+        DEFAULT_INT_SUBJECT,
+    };
+    use nsm_nitro_enclave_utils::verify::ErrorKind as VerifyErrorKind;
+    use std::time::{Duration, SystemTime};
+    use x509_cert::{
+        der::{
+            asn1::OctetString,
+            oid::{db::rfc5280::ID_KP_CLIENT_AUTH, AssociatedOid},
+        },
+        ext::pkix::{ExtendedKeyUsage, KeyUsage},
+    };
+
+    #[test]
+    fn end_extensions() {
+        let eku = ExtendedKeyUsage(vec![ID_KP_CLIENT_AUTH]);
+        let extension = Extension {
+            extn_id: ExtendedKeyUsage::OID,
+            critical: false,
+            extn_value: OctetString::new(eku.to_der().unwrap()).unwrap(),
+        };
+        let chain = NsmCertChain::builder(Duration::from_secs(60))
+            .end_extensions(vec![extension.clone()])
+            .build()
+            .unwrap();
+
+        let extensions = chain
+            .end_signer
+            .cert
+            .tbs_certificate
+            .extensions
+            .clone()
+            .unwrap();
+        assert!(extensions.contains(&extension));
+        // Only `clientAuth` is asserted, the verifier's default requires `serverAuth`
+        let err = chain.self_verify().unwrap_err();
+        assert_eq!(
+            err.verify_error().unwrap().kind(),
+            &VerifyErrorKind::KeyUsage
+        );
+
+        let key_usage = extensions
+            .iter()
+            .find(|extension| extension.extn_id == KeyUsage::OID)
+            .unwrap()
+            .clone();
+        let err = NsmCertChain::builder(Duration::from_secs(60))
+            .end_extensions(vec![key_usage])
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), &ErrorKind::Certificate);
+    }
+
+    #[test]
+    fn p256_chain() {
+        let chain = NsmCertChain::builder(Duration::from_secs(1))
+            .key_algorithm(KeyAlgorithm::P256)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            chain.end_signer.signing_key.key_algorithm(),
+            KeyAlgorithm::P256
+        );
+        // ecdsa-with-SHA256
+        assert_eq!(
+            chain.end_signer.cert.signature_algorithm.oid.to_string(),
+            "1.2.840.10045.4.3.2"
+        );
+    }
+
+    #[test]
+    fn zero_validity_is_rejected() {
+        let err = NsmCertChain::builder(Duration::ZERO).build().err().unwrap();
+        assert_eq!(err.kind(), &ErrorKind::Validity);
+    }
+
+    #[test]
+    fn configured_not_before() {
+        let hour = Duration::from_secs(60 * 60);
+        let not_before = SystemTime::now() - hour;
+        let chain = NsmCertChain::builder(hour * 2)
+            .not_before(not_before)
+            .build()
+            .unwrap();
+
+        for cert in [
+            &chain.root_signer.cert,
+            &chain.int_signer().cert,
+            &chain.end_signer.cert,
+        ] {
+            let validity = cert.tbs_certificate.validity;
+            let start = validity.not_before.to_system_time();
+            let end = validity.not_after.to_system_time();
+            // x509 times are truncated to the second
+            assert!(not_before.duration_since(start).unwrap() < Duration::from_secs(1));
+            assert_eq!(end.duration_since(start).unwrap(), hour * 2);
+        }
+    }
+
+    #[test]
+    fn random_serials_are_unique() {
+        let chain = NsmCertChain::generate(Duration::from_secs(1));
+
+        let root = &chain.root_signer.cert.tbs_certificate.serial_number;
+        let int = &chain.int_signer().cert.tbs_certificate.serial_number;
+        let end = &chain.end_signer.cert.tbs_certificate.serial_number;
+
+        assert_ne!(root, int);
+        assert_ne!(int, end);
+        assert_ne!(root, end);
+        assert_eq!(root.as_bytes().len(), 16);
+    }
+
+    #[test]
+    fn configured_serials() {
+        let chain = NsmCertChain::builder(Duration::from_secs(1))
+            .root_serial(SerialNumber::new(&[10]).unwrap())
+            .int_serial(SerialNumber::new(&[20]).unwrap())
+            .end_serial(SerialNumber::new(&[30]).unwrap())
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            chain
+                .root_signer
+                .cert
+                .tbs_certificate
+                .serial_number
+                .as_bytes(),
+            &[10]
+        );
+        assert_eq!(
+            chain
+                .int_signer()
+                .cert
+                .tbs_certificate
+                .serial_number
+                .as_bytes(),
+            &[20]
+        );
+        assert_eq!(
+            chain
+                .end_signer
+                .cert
+                .tbs_certificate
+                .serial_number
+                .as_bytes(),
+            &[30]
+        );
+    }
+
+    #[test]
+    fn multiple_intermediates() {
+        let chain = NsmCertChain::builder(Duration::from_secs(1))
+            .intermediate_count(3)
+            .build()
+            .unwrap();
+
+        let int_certs = chain.int_certs();
+        assert_eq!(int_certs.len(), 3);
+
+        let mut issuer = &chain.root_signer.cert.tbs_certificate.subject;
+        for cert in int_certs.iter().chain([&chain.end_signer.cert]) {
+            assert_eq!(&cert.tbs_certificate.issuer, issuer);
+            issuer = &cert.tbs_certificate.subject;
+        }
+
+        assert_eq!(
+            int_certs[0].tbs_certificate.subject.to_string(),
+            "OU=int-1,CN=dev-int"
+        );
+        assert_eq!(
+            chain.int_signer().cert.tbs_certificate.subject.to_string(),
+            DEFAULT_INT_SUBJECT
+        );
+    }
+
+    #[test]
+    fn seeded_chains_are_reproducible() {
+        let not_before = SystemTime::now();
+        let build = |seed| {
+            NsmCertChain::builder(Duration::from_secs(60))
+                .not_before(not_before)
+                .seed(seed)
+                .build()
+                .unwrap()
+        };
+        let encode = |chain: &NsmCertChain| chain.to_der_parts();
+
+        assert_eq!(encode(&build([1; 32])), encode(&build([1; 32])));
+        assert_ne!(encode(&build([1; 32])), encode(&build([2; 32])));
+    }
+
+    #[test]
+    fn zero_intermediates_is_rejected() {
+        let err = NsmCertChain::builder(Duration::from_secs(1))
+            .intermediate_count(0)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), &ErrorKind::IntermediateCount);
+    }
+}
@@ -0,0 +1,334 @@
+//! Provides base64 encoding utilities for [`NsmCertChain`], for embedding the certificates and signing keys in text based configuration, i.e. JSON or YAML.
+//! The DER encoded certificates and signing keys are encoded with the standard, padded, base64 alphabet from [RFC 4648](https://datatracker.ietf.org/doc/html/rfc4648#section-4), not the URL-safe alphabet.
+
+use ::base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{
+    de,
+    ser::{self, SerializeStruct},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt::Formatter;
+
+use super::{decoded_chain, serialize_int_signing_keys};
+use crate::{Certificate, DerDecodeExt, DerEncodeExt, NsmCertChain, SigningKey};
+
+/// Can be used in combination with [`base64_decoder`] to serialize and deserialize an [`NsmCertChain`] with base64 encoding.
+/// ```
+///  use serde::{Serialize, Deserialize};
+///  use nsm_nitro_enclave_utils_keygen::{NsmCertChain, encode::base64::{base64_encoder, base64_decoder}};
+///  #[derive(Serialize, Deserialize)]
+///  struct Example {
+///     #[serde(serialize_with = "base64_encoder", deserialize_with = "base64_decoder")]
+///     certs: NsmCertChain
+///  }
+/// ```
+pub fn base64_encoder<S>(v: &NsmCertChain, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    Base64NsmCertChain(v.clone()).serialize(s)
+}
+
+/// Can be used in combination with [`base64_encoder`] to serialize and deserialize an [`NsmCertChain`] with base64 encoding.
+/// ```
+///  use serde::{Serialize, Deserialize};
+///  use nsm_nitro_enclave_utils_keygen::{NsmCertChain, encode::base64::{base64_encoder, base64_decoder}};
+///  #[derive(Serialize, Deserialize)]
+///  struct Example {
+///     #[serde(serialize_with = "base64_encoder", deserialize_with = "base64_decoder")]
+///     certs: NsmCertChain
+///  }
+/// ```
+pub fn base64_decoder<'de, D>(deserializer: D) -> Result<NsmCertChain, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Base64NsmCertChain::deserialize(deserializer).map(|decoder| decoder.0)
+}
+
+#[doc(hidden)]
+/// A wrapper for [`NsmCertChain`] that serializes the inner certificates and signing keys to base64 encoded DER
+pub struct Base64NsmCertChain(pub NsmCertChain);
+
+impl Serialize for Base64NsmCertChain {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = serializer.serialize_struct("NsmCertChain", 6)?;
+
+        let root_cert_base64 = STANDARD.encode(
+            self.0
+                .root_signer
+                .cert
+                .to_der()
+                .map_err(ser::Error::custom)?,
+        );
+        let root_signing_key_base64 = self
+            .0
+            .root_signer
+            .signing_key
+            .as_ref()
+            .map(|signing_key| {
+                signing_key
+                    .to_pkcs8_der()
+                    .map(|der| STANDARD.encode(der.as_bytes()))
+            })
+            .transpose()
+            .map_err(ser::Error::custom)?;
+
+        s.serialize_field("rootCertificate", &root_cert_base64)?;
+        match root_signing_key_base64 {
+            Some(root_signing_key_base64) => {
+                s.serialize_field("rootSigningKey", &root_signing_key_base64)?
+            }
+            None => s.skip_field("rootSigningKey")?,
+        }
+
+        let int_cert_base64s = self
+            .0
+            .int_signers
+            .iter()
+            .map(|signer| signer.cert.to_der().map(|der| STANDARD.encode(der)))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(ser::Error::custom)?;
+        let int_signing_key_base64s = self
+            .0
+            .int_signers
+            .iter()
+            .map(|signer| {
+                signer
+                    .signing_key
+                    .as_ref()
+                    .map(|signing_key| {
+                        signing_key
+                            .to_pkcs8_der()
+                            .map(|der| STANDARD.encode(der.as_bytes()))
+                    })
+                    .transpose()
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(ser::Error::custom)?;
+
+        s.serialize_field("intCertificates", &int_cert_base64s)?;
+        serialize_int_signing_keys(&mut s, &int_signing_key_base64s)?;
+
+        let end_cert_base64 = STANDARD.encode(
+            self.0
+                .end_signer
+                .cert
+                .to_der()
+                .map_err(ser::Error::custom)?,
+        );
+        let end_signing_key_base64 = STANDARD.encode(
+            self.0
+                .end_signer
+                .signing_key
+                .to_pkcs8_der()
+                .map_err(ser::Error::custom)?
+                .as_bytes(),
+        );
+
+        s.serialize_field("endCertificate", &end_cert_base64)?;
+        s.serialize_field("endSigningKey", &end_signing_key_base64)?;
+
+        s.end()
+    }
+}
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "camelCase")]
+enum Field {
+    RootCertificate,
+    RootSigningKey,
+    IntCertificates,
+    /// The single intermediate certificate written by earlier versions of the keygen CLI
+    IntCertificate,
+    IntSigningKeys,
+    EndCertificate,
+    EndSigningKey,
+}
+
+impl<'de> Deserialize<'de> for Base64NsmCertChain {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Base64EncodedNsmCertChainVisitor;
+
+        impl<'de> de::Visitor<'de> for Base64EncodedNsmCertChainVisitor {
+            type Value = Base64NsmCertChain;
+
+            fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+                formatter.write_str("struct Base64EncodedNsmCertChain")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::MapAccess<'de>,
+            {
+                let mut root_certificate = None;
+                let mut root_signing_key = None;
+                let mut int_certificates = None;
+                let mut int_signing_keys = None;
+                let mut end_certificate = None;
+                let mut end_signing_key = None;
+                while let Some(key) = map.next_key()? {
+                    match key {
+                        Field::RootCertificate => {
+                            if root_certificate.is_some() {
+                                return Err(de::Error::duplicate_field("rootCertificate"));
+                            }
+                            root_certificate =
+                                Some(decode_certificate(&map.next_value::<String>()?)?);
+                        }
+                        Field::RootSigningKey => {
+                            if root_signing_key.is_some() {
+                                return Err(de::Error::duplicate_field("rootSigningKey"));
+                            }
+                            root_signing_key =
+                                Some(decode_signing_key(&map.next_value::<String>()?)?);
+                        }
+                        Field::IntCertificates => {
+                            if int_certificates.is_some() {
+                                return Err(de::Error::duplicate_field("intCertificates"));
+                            }
+                            int_certificates = Some(
+                                map.next_value::<Vec<String>>()?
+                                    .iter()
+                                    .map(|s| decode_certificate(s))
+                                    .collect::<Result<Vec<_>, _>>()?,
+                            );
+                        }
+                        Field::IntCertificate => {
+                            if int_certificates.is_some() {
+                                return Err(de::Error::duplicate_field("intCertificates"));
+                            }
+                            int_certificates =
+                                Some(vec![decode_certificate(&map.next_value::<String>()?)?]);
+                        }
+                        Field::IntSigningKeys => {
+                            if int_signing_keys.is_some() {
+                                return Err(de::Error::duplicate_field("intSigningKeys"));
+                            }
+                            int_signing_keys = Some(
+                                map.next_value::<Vec<Option<String>>>()?
+                                    .iter()
+                                    .map(|s| s.as_deref().map(decode_signing_key).transpose())
+                                    .collect::<Result<Vec<_>, _>>()?,
+                            );
+                        }
+                        Field::EndCertificate => {
+                            if end_certificate.is_some() {
+                                return Err(de::Error::duplicate_field("endCertificate"));
+                            }
+                            end_certificate =
+                                Some(decode_certificate(&map.next_value::<String>()?)?);
+                        }
+                        Field::EndSigningKey => {
+                            if end_signing_key.is_some() {
+                                return Err(de::Error::duplicate_field("endSigningKey"));
+                            }
+                            end_signing_key =
+                                Some(decode_signing_key(&map.next_value::<String>()?)?);
+                        }
+                    }
+                }
+
+                decoded_chain(
+                    root_certificate,
+                    root_signing_key,
+                    int_certificates,
+                    int_signing_keys,
+                    end_certificate,
+                    end_signing_key,
+                )
+                .map(Base64NsmCertChain)
+            }
+        }
+
+        const FIELDS: &[&str] = &[
+            "rootCertificate",
+            "rootSigningKey",
+            "intCertificates",
+            "intCertificate",
+            "intSigningKeys",
+            "endCertificate",
+            "endSigningKey",
+        ];
+        deserializer.deserialize_struct(
+            "Base64EncodedNsmCertChain",
+            FIELDS,
+            Base64EncodedNsmCertChainVisitor,
+        )
+    }
+}
+
+/// Decodes a base64 encoded, DER encoded, certificate
+fn decode_certificate<E: de::Error>(s: &str) -> Result<Certificate, E> {
+    let der = STANDARD.decode(s).map_err(E::custom)?;
+    Certificate::from_der(&der).map_err(E::custom)
+}
+
+/// Decodes a base64 encoded, DER encoded, PKCS#8 signing key
+fn decode_signing_key<E: de::Error>(s: &str) -> Result<SigningKey, E> {
+    let der = STANDARD.decode(s).map_err(E::custom)?;
+    SigningKey::from_pkcs8_der(&der).map_err(E::custom)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{base64_decoder, base64_encoder, Base64NsmCertChain};
+    use crate::{DerEncodeExt, NsmCertChain};
+    use ::base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{Deserialize, Serialize};
+    use std::sync::LazyLock;
+    use std::time::Duration;
+
+    static CERT_CHAIN: LazyLock<NsmCertChain> =
+        LazyLock::new(|| NsmCertChain::generate(Duration::from_secs(1)));
+
+    #[test]
+    fn base64_encode_decode() {
+        let base64 = serde_json::to_string(&Base64NsmCertChain(CERT_CHAIN.clone())).unwrap();
+        let certs: Base64NsmCertChain = serde_json::from_str(&base64).unwrap();
+        // `assert_eq!` would print the signing keys on failure
+        assert!(certs.0.to_der_parts() == CERT_CHAIN.to_der_parts());
+    }
+
+    #[test]
+    fn base64_serialize_deserialize_with() {
+        #[derive(Serialize, Deserialize)]
+        struct Example {
+            #[serde(serialize_with = "base64_encoder", deserialize_with = "base64_decoder")]
+            certs: NsmCertChain,
+        }
+
+        let example = Example {
+            certs: CERT_CHAIN.clone(),
+        };
+
+        let json = serde_json::to_string(&example).unwrap();
+
+        let _example: Example = serde_json::from_str(&json).unwrap();
+    }
+
+    #[test]
+    fn standard_alphabet() {
+        let json = serde_json::to_value(Base64NsmCertChain(CERT_CHAIN.clone())).unwrap();
+        assert_eq!(
+            json["rootCertificate"],
+            STANDARD.encode(CERT_CHAIN.root_signer.cert.to_der().unwrap())
+        );
+    }
+
+    #[test]
+    fn invalid_base64_is_rejected() {
+        let mut json = serde_json::to_value(Base64NsmCertChain(CERT_CHAIN.clone())).unwrap();
+        json["rootCertificate"] = "not base64!".into();
+
+        let err = serde_json::from_value::<Base64NsmCertChain>(json)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Invalid"));
+    }
+}
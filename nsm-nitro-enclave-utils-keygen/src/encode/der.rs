@@ -1,7 +1,5 @@
 //! Provides DER encoding utilities for [`NsmCertChain`]
 
-use p384::ecdsa::SigningKey;
-use p384::pkcs8::{DecodePrivateKey, EncodePrivateKey};
 use serde::{
     de,
     ser::{self, SerializeStruct},
@@ -10,7 +8,8 @@ use serde::{
 use serde_bytes::ByteBuf;
 use std::fmt::Formatter;
 
-use crate::{Certificate, DerDecodeExt, DerEncodeExt, EndCertificateSigner, NsmCertChain};
+use super::{decoded_chain, serialize_int_signing_keys};
+use crate::{Certificate, DerDecodeExt, DerEncodeExt, NsmCertChain, SigningKey};
 
 /// Can be used in combination with [`der_decoder`] to serialize and deserialize an [`NsmCertChain`] with DER encoding.
 /// ```
@@ -47,7 +46,7 @@ where
 }
 
 #[doc(hidden)]
-/// A wrapper for [`NsmCertChain`] that serializes the inner certificates and signing key to DER bytes
+/// A wrapper for [`NsmCertChain`] that serializes the inner certificates and signing keys to DER bytes
 pub struct DerNsmCertChain(pub NsmCertChain);
 
 impl Serialize for DerNsmCertChain {
@@ -55,32 +54,77 @@ impl Serialize for DerNsmCertChain {
     where
         S: Serializer,
     {
-        let mut s = serializer.serialize_struct("NsmCertChain", 3)?;
+        let mut s = serializer.serialize_struct("NsmCertChain", 6)?;
 
-        let root_der = ByteBuf::from(
+        let root_cert_der = ByteBuf::from(
             self.0
-                .root
+                .root_signer
+                .cert
                 .to_der()
-                .map_err(|err| ser::Error::custom(err))?,
+                .map_err(ser::Error::custom)?,
         );
-        s.serialize_field("rootCertificate", &root_der)?;
+        let root_signing_key_der = self
+            .0
+            .root_signer
+            .signing_key
+            .as_ref()
+            .map(|signing_key| {
+                signing_key
+                    .to_pkcs8_der()
+                    .map(|der| ByteBuf::from(der.as_bytes()))
+            })
+            .transpose()
+            .map_err(ser::Error::custom)?;
+
+        s.serialize_field("rootCertificate", &root_cert_der)?;
+        match root_signing_key_der {
+            Some(root_signing_key_der) => {
+                s.serialize_field("rootSigningKey", &root_signing_key_der)?
+            }
+            None => s.skip_field("rootSigningKey")?,
+        }
+
+        let int_cert_ders = self
+            .0
+            .int_signers
+            .iter()
+            .map(|signer| signer.cert.to_der().map(ByteBuf::from))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(ser::Error::custom)?;
+        let int_signing_key_ders = self
+            .0
+            .int_signers
+            .iter()
+            .map(|signer| {
+                signer
+                    .signing_key
+                    .as_ref()
+                    .map(|signing_key| {
+                        signing_key
+                            .to_pkcs8_der()
+                            .map(|der| ByteBuf::from(der.as_bytes()))
+                    })
+                    .transpose()
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(ser::Error::custom)?;
 
-        let int_der = ByteBuf::from(self.0.int.to_der().map_err(|err| ser::Error::custom(err))?);
-        s.serialize_field("intCertificate", &int_der)?;
+        s.serialize_field("intCertificates", &int_cert_ders)?;
+        serialize_int_signing_keys(&mut s, &int_signing_key_ders)?;
 
         let end_cert_der = ByteBuf::from(
             self.0
                 .end_signer
                 .cert
                 .to_der()
-                .map_err(|err| ser::Error::custom(err))?,
+                .map_err(ser::Error::custom)?,
         );
         let end_signing_key_der = ByteBuf::from(
             self.0
                 .end_signer
                 .signing_key
                 .to_pkcs8_der()
-                .map_err(|err| ser::Error::custom(err))?
+                .map_err(ser::Error::custom)?
                 .as_bytes(),
         );
 
@@ -94,7 +138,11 @@ impl Serialize for DerNsmCertChain {
 #[serde(field_identifier, rename_all = "camelCase")]
 enum Field {
     RootCertificate,
+    RootSigningKey,
+    IntCertificates,
+    /// The single intermediate certificate written by earlier versions of the keygen CLI
     IntCertificate,
+    IntSigningKeys,
     EndCertificate,
     EndSigningKey,
 }
@@ -118,7 +166,9 @@ impl<'de> Deserialize<'de> for DerNsmCertChain {
                 A: de::MapAccess<'de>,
             {
                 let mut root_certificate = None;
-                let mut int_certificate = None;
+                let mut root_signing_key = None;
+                let mut int_certificates = None;
+                let mut int_signing_keys = None;
                 let mut end_certificate = None;
                 let mut end_signing_key = None;
                 while let Some(key) = map.next_key()? {
@@ -129,24 +179,63 @@ impl<'de> Deserialize<'de> for DerNsmCertChain {
                             }
                             root_certificate =
                                 Some(map.next_value().map(|bytes: Vec<u8>| {
-                                    Certificate::from_der(&bytes)
-                                        .map_err(|err| de::Error::custom(err))
+                                    Certificate::from_der(&bytes).map_err(de::Error::custom)
+                                })??);
+                        }
+                        Field::RootSigningKey => {
+                            if root_signing_key.is_some() {
+                                return Err(de::Error::duplicate_field("rootSigningKey"));
+                            }
+                            root_signing_key =
+                                Some(map.next_value().map(|bytes: Vec<u8>| {
+                                    SigningKey::from_pkcs8_der(&bytes).map_err(de::Error::custom)
+                                })??);
+                        }
+                        Field::IntCertificates => {
+                            if int_certificates.is_some() {
+                                return Err(de::Error::duplicate_field("intCertificates"));
+                            }
+                            int_certificates =
+                                Some(map.next_value().map(|certs: Vec<Vec<u8>>| {
+                                    certs
+                                        .iter()
+                                        .map(|bytes| Certificate::from_der(bytes))
+                                        .collect::<Result<Vec<_>, _>>()
+                                        .map_err(de::Error::custom)
                                 })??);
                         }
                         Field::IntCertificate => {
-                            if int_certificate.is_some() {
-                                return Err(de::Error::duplicate_field("intCertificate"));
+                            if int_certificates.is_some() {
+                                return Err(de::Error::duplicate_field("intCertificates"));
                             }
-                            int_certificate = Some(map.next_value().map(|bytes: Vec<u8>| {
-                                Certificate::from_der(&bytes).map_err(|err| de::Error::custom(err))
-                            })??);
+                            int_certificates =
+                                Some(vec![map.next_value().map(|bytes: Vec<u8>| {
+                                    Certificate::from_der(&bytes).map_err(de::Error::custom)
+                                })??]);
+                        }
+                        Field::IntSigningKeys => {
+                            if int_signing_keys.is_some() {
+                                return Err(de::Error::duplicate_field("intSigningKeys"));
+                            }
+                            int_signing_keys =
+                                Some(map.next_value().map(|keys: Vec<Option<Vec<u8>>>| {
+                                    keys.iter()
+                                        .map(|bytes| {
+                                            bytes
+                                                .as_deref()
+                                                .map(SigningKey::from_pkcs8_der)
+                                                .transpose()
+                                        })
+                                        .collect::<Result<Vec<_>, _>>()
+                                        .map_err(de::Error::custom)
+                                })??);
                         }
                         Field::EndCertificate => {
                             if end_certificate.is_some() {
                                 return Err(de::Error::duplicate_field("endCertificate"));
                             }
                             end_certificate = Some(map.next_value().map(|bytes: Vec<u8>| {
-                                Certificate::from_der(&bytes).map_err(|err| de::Error::custom(err))
+                                Certificate::from_der(&bytes).map_err(de::Error::custom)
                             })??);
                         }
                         Field::EndSigningKey => {
@@ -154,36 +243,30 @@ impl<'de> Deserialize<'de> for DerNsmCertChain {
                                 return Err(de::Error::duplicate_field("endSigningKey"));
                             }
                             end_signing_key = Some(map.next_value().map(|bytes: Vec<u8>| {
-                                SigningKey::from_pkcs8_der(&bytes)
-                                    .map_err(|err| de::Error::custom(err))
+                                SigningKey::from_pkcs8_der(&bytes).map_err(de::Error::custom)
                             })??);
                         }
                     }
                 }
 
-                let root_certificate =
-                    root_certificate.ok_or_else(|| de::Error::missing_field("rootCertificate"))?;
-                let int_certificate =
-                    int_certificate.ok_or_else(|| de::Error::missing_field("intCertificate"))?;
-                let end_certificate =
-                    end_certificate.ok_or_else(|| de::Error::missing_field("endCertificate"))?;
-                let end_signing_key =
-                    end_signing_key.ok_or_else(|| de::Error::missing_field("endSigningKey"))?;
-
-                Ok(DerNsmCertChain(NsmCertChain {
-                    root: root_certificate,
-                    int: int_certificate,
-                    end_signer: EndCertificateSigner {
-                        cert: end_certificate,
-                        signing_key: end_signing_key,
-                    },
-                }))
+                decoded_chain(
+                    root_certificate,
+                    root_signing_key,
+                    int_certificates,
+                    int_signing_keys,
+                    end_certificate,
+                    end_signing_key,
+                )
+                .map(DerNsmCertChain)
             }
         }
 
         const FIELDS: &[&str] = &[
             "rootCertificate",
+            "rootSigningKey",
+            "intCertificates",
             "intCertificate",
+            "intSigningKeys",
             "endCertificate",
             "endSigningKey",
         ];
@@ -198,6 +281,7 @@ impl<'de> Deserialize<'de> for DerNsmCertChain {
 #[cfg(test)]
 mod test {
     use super::{der_decoder, der_encoder, DerNsmCertChain};
+    use crate::encode::pem::PemNsmCertChain;
     use crate::NsmCertChain;
     use serde::{Deserialize, Serialize};
     use std::sync::LazyLock;
@@ -229,4 +313,10 @@ mod test {
 
         let _example: Example = serde_json::from_str(&json).unwrap();
     }
+
+    #[test]
+    fn pem_is_rejected() {
+        let pem = serde_json::to_string(&PemNsmCertChain(CERT_CHAIN.clone())).unwrap();
+        assert!(serde_json::from_str::<DerNsmCertChain>(&pem).is_err());
+    }
 }
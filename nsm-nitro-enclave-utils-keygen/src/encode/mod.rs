@@ -1,4 +1,76 @@
 //! Provides encoding utilities for [`NsmCertChain`]
+//!
+//! The root and intermediate signing keys are optional when decoding, as bundles written by earlier versions of the keygen CLI only contain the root and intermediate certificates.
+//! Those chains decode with [`CertificateIssuer::signing_key`] set to `None`, and are encoded without the missing keys.
 
+pub mod base64;
 pub mod der;
 pub mod pem;
+pub mod pkcs12;
+
+use serde::{de, ser::SerializeStruct, Serialize};
+
+use crate::{Certificate, CertificateIssuer, CertificateSigner, NsmCertChain, SigningKey};
+
+/// Serializes the intermediate signing keys, with a `null` for every intermediate whose signing key isn't known.
+/// The field is skipped if none of them are known.
+fn serialize_int_signing_keys<S, T>(
+    s: &mut S,
+    int_signing_keys: &[Option<T>],
+) -> Result<(), S::Error>
+where
+    S: SerializeStruct,
+    T: Serialize,
+{
+    if int_signing_keys.iter().all(Option::is_none) {
+        return s.skip_field("intSigningKeys");
+    }
+    s.serialize_field("intSigningKeys", int_signing_keys)
+}
+
+/// Assembles a decoded [`NsmCertChain`], checking that the required fields are present and that there's at least one intermediate.
+fn decoded_chain<E: de::Error>(
+    root_certificate: Option<Certificate>,
+    root_signing_key: Option<SigningKey>,
+    int_certificates: Option<Vec<Certificate>>,
+    int_signing_keys: Option<Vec<Option<SigningKey>>>,
+    end_certificate: Option<Certificate>,
+    end_signing_key: Option<SigningKey>,
+) -> Result<NsmCertChain, E> {
+    let root_certificate =
+        root_certificate.ok_or_else(|| de::Error::missing_field("rootCertificate"))?;
+    let int_certificates =
+        int_certificates.ok_or_else(|| de::Error::missing_field("intCertificates"))?;
+    let end_certificate =
+        end_certificate.ok_or_else(|| de::Error::missing_field("endCertificate"))?;
+    let end_signing_key =
+        end_signing_key.ok_or_else(|| de::Error::missing_field("endSigningKey"))?;
+
+    if int_certificates.is_empty() {
+        return Err(de::Error::invalid_length(0, &"at least one intermediate"));
+    }
+    let int_signing_keys = int_signing_keys.unwrap_or_else(|| vec![None; int_certificates.len()]);
+    if int_certificates.len() != int_signing_keys.len() {
+        return Err(de::Error::invalid_length(
+            int_signing_keys.len(),
+            &"a signing key for every intermediate certificate",
+        ));
+    }
+    let int_signers = int_certificates
+        .into_iter()
+        .zip(int_signing_keys)
+        .map(|(cert, signing_key)| CertificateIssuer { cert, signing_key })
+        .collect();
+
+    Ok(NsmCertChain {
+        root_signer: CertificateIssuer {
+            cert: root_certificate,
+            signing_key: root_signing_key,
+        },
+        int_signers,
+        end_signer: CertificateSigner {
+            cert: end_certificate,
+            signing_key: end_signing_key,
+        },
+    })
+}
@@ -0,0 +1,106 @@
+//! Provides PKCS#12 encoding utilities for [`NsmCertChain`]
+
+use p12_keystore::{KeyStore, KeyStoreEntry, PrivateKeyChain};
+use sha2::{Digest, Sha256};
+
+use crate::{DerEncodeExt, Error, ErrorKind, NsmCertChain};
+
+/// The alias of the end signer's entry inside the PKCS#12 archive
+pub const END_SIGNER_ALIAS: &str = "end-signer";
+
+/// Encodes the end signer of an [`NsmCertChain`] as a password-protected PKCS#12 archive, i.e. a `.p12` file.
+///
+/// The archive contains a single private key entry, aliased [`END_SIGNER_ALIAS`], holding the end signing key and its certificate chain.
+/// The chain is ordered leaf-first: the end certificate, followed by the intermediates (starting with the one that signed the end certificate), followed by the root certificate.
+/// An empty `password` is allowed.
+pub fn pkcs12_encode(v: &NsmCertChain, password: &str) -> Result<Vec<u8>, Error> {
+    let end_cert = v
+        .end_signer
+        .cert
+        .to_der()
+        .map_err(|err| Error::new(ErrorKind::Encoding, err))?;
+    let signing_key = v
+        .end_signer
+        .signing_key
+        .to_pkcs8_der()
+        .map_err(|err| Error::new(ErrorKind::Encoding, err))?;
+
+    let mut chain = vec![end_cert.clone()];
+    for int_signer in v.int_signers.iter().rev() {
+        chain.push(
+            int_signer
+                .cert
+                .to_der()
+                .map_err(|err| Error::new(ErrorKind::Encoding, err))?,
+        );
+    }
+    chain.push(
+        v.root_signer
+            .cert
+            .to_der()
+            .map_err(|err| Error::new(ErrorKind::Encoding, err))?,
+    );
+    let chain = chain
+        .iter()
+        .map(|der| p12_keystore::Certificate::from_der(der))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| Error::new(ErrorKind::Encoding, err))?;
+
+    // The local key id links the private key to its certificate, the digest of the certificate is a common choice.
+    let local_key_id = Sha256::digest(&end_cert);
+
+    let mut key_store = KeyStore::new();
+    key_store.add_entry(
+        END_SIGNER_ALIAS,
+        KeyStoreEntry::PrivateKeyChain(PrivateKeyChain::new(
+            signing_key.as_bytes(),
+            local_key_id,
+            chain,
+        )),
+    );
+
+    key_store
+        .writer(password)
+        .write()
+        .map_err(|err| Error::new(ErrorKind::Encoding, err))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{pkcs12_encode, END_SIGNER_ALIAS};
+    use crate::{DerEncodeExt, NsmCertChain};
+    use p12_keystore::KeyStore;
+    use std::time::Duration;
+
+    #[test]
+    fn pkcs12_round_trip() {
+        let chain = NsmCertChain::generate(Duration::from_secs(1));
+
+        for password in ["", "password"] {
+            let p12 = pkcs12_encode(&chain, password).unwrap();
+            let key_store = KeyStore::from_pkcs12(&p12, password).unwrap();
+
+            let (alias, key_chain) = key_store.private_key_chain().unwrap();
+            assert_eq!(alias, END_SIGNER_ALIAS);
+            assert_eq!(
+                key_chain.key(),
+                chain
+                    .end_signer
+                    .signing_key
+                    .to_pkcs8_der()
+                    .unwrap()
+                    .as_bytes()
+            );
+
+            let expected = [
+                &chain.end_signer.cert,
+                &chain.int_signer().cert,
+                &chain.root_signer.cert,
+            ];
+            assert_eq!(key_chain.chain().len(), expected.len());
+            for (cert, expected) in key_chain.chain().iter().zip(expected) {
+                assert_eq!(cert.as_der(), expected.to_der().unwrap());
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! Provides [`SigningKey`], which wraps the private keys of an [`NsmCertChain`](crate::NsmCertChain) for every supported [`KeyAlgorithm`].
+
+use p384::pkcs8::{
+    der::zeroize::Zeroizing, DecodePrivateKey, EncodePrivateKey, LineEnding, SecretDocument,
+};
+use rand::{CryptoRng, RngCore};
+use x509_cert::spki::{EncodePublicKey, SubjectPublicKeyInfoOwned};
+
+/// The algorithm used to generate every key in an [`NsmCertChain`](crate::NsmCertChain)
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum KeyAlgorithm {
+    /// ECDSA using the NIST P-256 curve, signing with SHA-256.
+    /// Authentic attestation documents are never signed with P-256, making this useful for testing that verifiers reject unexpected curves.
+    P256,
+    /// ECDSA using the NIST P-384 curve, signing with SHA-384. This is what AWS uses to sign authentic attestation documents.
+    #[default]
+    P384,
+}
+
+/// A private key whose curve corresponds to a [`KeyAlgorithm`]
+#[derive(Clone, Debug)]
+pub enum SigningKey {
+    P256(p256::ecdsa::SigningKey),
+    P384(p384::ecdsa::SigningKey),
+}
+
+impl From<p256::ecdsa::SigningKey> for SigningKey {
+    fn from(signing_key: p256::ecdsa::SigningKey) -> Self {
+        Self::P256(signing_key)
+    }
+}
+
+impl From<p384::ecdsa::SigningKey> for SigningKey {
+    fn from(signing_key: p384::ecdsa::SigningKey) -> Self {
+        Self::P384(signing_key)
+    }
+}
+
+impl SigningKey {
+    /// Generates a new random [`SigningKey`] for the provided [`KeyAlgorithm`]
+    pub fn generate(key_algorithm: KeyAlgorithm) -> Self {
+        Self::generate_with_rng(key_algorithm, &mut rand::thread_rng())
+    }
+
+    /// Generates a new [`SigningKey`] for the provided [`KeyAlgorithm`] using `rng`.
+    /// A seeded `rng` generates the same key every time, which is useful for reproducible tests.
+    pub fn generate_with_rng<R>(key_algorithm: KeyAlgorithm, rng: &mut R) -> Self
+    where
+        R: RngCore + CryptoRng,
+    {
+        match key_algorithm {
+            KeyAlgorithm::P256 => Self::P256(p256::ecdsa::SigningKey::random(rng)),
+            KeyAlgorithm::P384 => Self::P384(p384::ecdsa::SigningKey::random(rng)),
+        }
+    }
+
+    /// The [`KeyAlgorithm`] this key was generated with
+    pub fn key_algorithm(&self) -> KeyAlgorithm {
+        match self {
+            Self::P256(_) => KeyAlgorithm::P256,
+            Self::P384(_) => KeyAlgorithm::P384,
+        }
+    }
+
+    /// Returns the inner P-384 key, if this key uses [`KeyAlgorithm::P384`].
+    /// This is the key type expected by `nsm-nitro-enclave-utils` when signing attestation documents.
+    pub fn as_p384(&self) -> Option<&p384::ecdsa::SigningKey> {
+        match self {
+            Self::P384(signing_key) => Some(signing_key),
+            _ => None,
+        }
+    }
+
+    /// Returns the inner P-256 key, if this key uses [`KeyAlgorithm::P256`].
+    pub fn as_p256(&self) -> Option<&p256::ecdsa::SigningKey> {
+        match self {
+            Self::P256(signing_key) => Some(signing_key),
+            _ => None,
+        }
+    }
+
+    /// Encodes the public half of this key as a `SubjectPublicKeyInfo`
+    pub fn public_key_info(&self) -> x509_cert::spki::Result<SubjectPublicKeyInfoOwned> {
+        let der = match self {
+            Self::P256(signing_key) => signing_key.verifying_key().to_public_key_der()?,
+            Self::P384(signing_key) => signing_key.verifying_key().to_public_key_der()?,
+        };
+        SubjectPublicKeyInfoOwned::try_from(der.as_bytes()).map_err(Into::into)
+    }
+
+    /// Encodes this key as a DER encoded PKCS#8 document
+    pub fn to_pkcs8_der(&self) -> p384::pkcs8::Result<SecretDocument> {
+        match self {
+            Self::P256(signing_key) => signing_key.to_pkcs8_der(),
+            Self::P384(signing_key) => signing_key.to_pkcs8_der(),
+        }
+    }
+
+    /// Encodes this key as a PEM encoded PKCS#8 document
+    pub fn to_pkcs8_pem(&self, line_ending: LineEnding) -> p384::pkcs8::Result<Zeroizing<String>> {
+        match self {
+            Self::P256(signing_key) => signing_key.to_pkcs8_pem(line_ending),
+            Self::P384(signing_key) => signing_key.to_pkcs8_pem(line_ending),
+        }
+    }
+
+    /// Decodes a DER encoded PKCS#8 document. The curve is determined by the document's algorithm parameters.
+    pub fn from_pkcs8_der(bytes: &[u8]) -> p384::pkcs8::Result<Self> {
+        p384::ecdsa::SigningKey::from_pkcs8_der(bytes)
+            .map(Self::P384)
+            .or_else(|_| p256::ecdsa::SigningKey::from_pkcs8_der(bytes).map(Self::P256))
+    }
+
+    /// Decodes a PEM encoded PKCS#8 document. The curve is determined by the document's algorithm parameters.
+    pub fn from_pkcs8_pem(s: &str) -> p384::pkcs8::Result<Self> {
+        p384::ecdsa::SigningKey::from_pkcs8_pem(s)
+            .map(Self::P384)
+            .or_else(|_| p256::ecdsa::SigningKey::from_pkcs8_pem(s).map(Self::P256))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{KeyAlgorithm, SigningKey};
+
+    #[test]
+    fn pkcs8_round_trip() {
+        for key_algorithm in [KeyAlgorithm::P256, KeyAlgorithm::P384] {
+            let signing_key = SigningKey::generate(key_algorithm);
+            let der = signing_key.to_pkcs8_der().unwrap();
+            let decoded = SigningKey::from_pkcs8_der(der.as_bytes()).unwrap();
+            assert_eq!(decoded.key_algorithm(), key_algorithm);
+            assert_eq!(decoded.to_pkcs8_der().unwrap().as_bytes(), der.as_bytes());
+        }
+    }
+}
@@ -1,123 +1,821 @@
 //! A small collection of utilities used to generated [`NsmCertChain`], which is used by [nsm-nitro-enclave-utils](https://crates.io/crates/nsm-nitro-enclave-utils) to self-sign attestation documents in local development environments.
 
+pub mod builder;
 pub mod encode;
+pub mod key;
 
-use p384::{
-    ecdsa::{DerSignature, SigningKey, VerifyingKey},
-    SecretKey,
-};
-use std::time::Duration;
-use x509_cert::{
-    builder::{Builder, CertificateBuilder, Profile},
-    name::Name,
-    serial_number::SerialNumber,
-    spki::SubjectPublicKeyInfo,
-    time::Validity,
+pub use builder::NsmCertChainBuilder;
+pub use key::{KeyAlgorithm, SigningKey};
+
+use nsm_nitro_enclave_utils::{
+    api::{
+        nsm::{AttestationDoc, ErrorCode, Request, Response},
+        ByteBuf,
+    },
+    driver::{
+        dev::{DevNitro, DevNitroBuilder},
+        Driver,
+    },
+    time::Time,
+    verify::{AttestationDocVerifierExt, VerifyError},
 };
+use std::time::{Duration, SystemTime};
 
 pub use x509_cert::{
+    crl::CertificateList,
     der::{Decode as DerDecodeExt, Encode as DerEncodeExt, EncodePem as PemEncodeExt},
+    ext::Extension,
+    name::Name,
+    serial_number::SerialNumber,
     Certificate,
 };
 
-/// A bundle that comprises every certificate (and an end signing key) that is used by [nsm-nitro-enclave-utils](https://crates.io/crates/nsm-nitro-enclave-utils) to self-sign attestation documents in local development environments.
+/// The subject used for the root certificate by [`NsmCertChain::generate`]
+pub const DEFAULT_ROOT_SUBJECT: &str = "CN=dev-root";
+/// The subject used for the intermediate certificate by [`NsmCertChain::generate`]
+pub const DEFAULT_INT_SUBJECT: &str = "CN=dev-int";
+/// The subject used for the end certificate by [`NsmCertChain::generate`]
+pub const DEFAULT_END_SUBJECT: &str = "CN=dev-end";
+
+/// The lowercase hex encoded SHA-384 digest of `cert`'s DER encoding, commonly used to identify a pinned root certificate.
+///
+/// Panics if `cert` can't be DER encoded, which doesn't happen to certificates that were decoded or generated by [`NsmCertChain`].
+pub fn fingerprint_sha384(cert: &Certificate) -> String {
+    use sha2::{Digest, Sha384};
+
+    let der = cert.to_der().expect("Failed to DER encode certificate");
+    hex::encode(Sha384::digest(der))
+}
+
+/// A bundle that comprises every certificate (and its signing key) that is used by [nsm-nitro-enclave-utils](https://crates.io/crates/nsm-nitro-enclave-utils) to self-sign attestation documents in local development environments.
+///
+/// The root and intermediate signing keys can be used to issue additional certificates that chain up to the same root.
+/// Bundles written before the keygen CLI exported them only contain the root and intermediate certificates, see [`CertificateIssuer`].
 #[derive(Clone)]
 pub struct NsmCertChain {
-    pub root: Certificate,
-    pub int: Certificate,
-    pub end_signer: EndCertificateSigner,
+    pub root_signer: CertificateIssuer,
+    /// Ordered from the intermediate signed by the root to the intermediate that signed the end certificate.
+    /// Private to guarantee it's never empty, see [`NsmCertChain::new`] and [`NsmCertChain::int_signers`].
+    int_signers: Vec<CertificateIssuer>,
+    /// The end certificate asserts the `digitalSignature` and `nonRepudiation` key usages, and no extended key usage.
+    pub end_signer: CertificateSigner,
 }
 
-/// Contains the end certificate and its associated signing key
+/// Contains a certificate and its associated signing key
 #[derive(Clone)]
-pub struct EndCertificateSigner {
+pub struct CertificateSigner {
     pub cert: Certificate,
     pub signing_key: SigningKey,
 }
 
+/// Contains a root or intermediate certificate and, if it's known, its associated signing key.
+///
+/// `signing_key` is `None` for bundles that only stored the root and intermediate certificates, i.e. the `rootCertificate`, `intCertificate`, `endCertificate`, and `endSigningKey` JSON written by earlier versions of the keygen CLI.
+/// Those chains still sign attestation documents, but can't issue additional certificates or CRLs.
+#[derive(Clone)]
+pub struct CertificateIssuer {
+    pub cert: Certificate,
+    pub signing_key: Option<SigningKey>,
+}
+
+impl CertificateIssuer {
+    /// The signing key, or an [`ErrorKind::MissingSigningKey`] error if the bundle didn't include it
+    pub(crate) fn require_signing_key(&self) -> Result<&SigningKey, Error> {
+        self.signing_key.as_ref().ok_or_else(|| {
+            Error::new(
+                ErrorKind::MissingSigningKey,
+                ErrorContext("The issuer's signing key isn't part of this NsmCertChain"),
+            )
+        })
+    }
+}
+
+impl From<CertificateSigner> for CertificateIssuer {
+    fn from(signer: CertificateSigner) -> Self {
+        Self {
+            cert: signer.cert,
+            signing_key: Some(signer.signing_key),
+        }
+    }
+}
+
 impl NsmCertChain {
-    /// Generates an [`NsmCertChain`] that is valid until the specified [`Duration`].
+    /// Creates an [`NsmCertChainBuilder`] for an [`NsmCertChain`] that is valid until the specified [`Duration`].
+    pub fn builder(valid_until: Duration) -> NsmCertChainBuilder {
+        NsmCertChainBuilder::new(valid_until)
+    }
+
+    /// Assembles an [`NsmCertChain`] from existing certificates and signing keys.
+    /// `int_signers` is ordered from the intermediate signed by the root to the intermediate that signed the end certificate.
     ///
-    /// These functions are not designed to be called inside a server and can theoretically panic, though that isn't expected behavior.
-    pub fn generate(valid_until: Duration) -> Self {
-        let (root_signing_key, root_public_key) = generate_key();
-        let root_cert = build_cert(
-            Profile::Root,
-            root_signing_key.clone(),
-            root_public_key,
-            valid_until,
-        );
+    /// Fails with [`ErrorKind::IntermediateCount`] if `int_signers` is empty.
+    pub fn new(
+        root_signer: CertificateIssuer,
+        int_signers: Vec<CertificateIssuer>,
+        end_signer: CertificateSigner,
+    ) -> Result<Self, Error> {
+        if int_signers.is_empty() {
+            return Err(Error::new(
+                ErrorKind::IntermediateCount,
+                ErrorContext("An NsmCertChain requires at least one intermediate"),
+            ));
+        }
+
+        Ok(Self {
+            root_signer,
+            int_signers,
+            end_signer,
+        })
+    }
+
+    /// The intermediate signers, ordered from the one signed by the root to the one that signed the end certificate. Never empty.
+    pub fn int_signers(&self) -> &[CertificateIssuer] {
+        &self.int_signers
+    }
+
+    /// The intermediate certificates, ordered from the one signed by the root to the one that signed the end certificate.
+    /// This is the order expected by an attestation document's `cabundle`, following the root certificate.
+    pub fn int_certs(&self) -> Vec<Certificate> {
+        self.int_signers
+            .iter()
+            .map(|signer| signer.cert.clone())
+            .collect()
+    }
+
+    /// The intermediate that signed the end certificate
+    pub fn int_signer(&self) -> &CertificateIssuer {
+        self.int_signers
+            .last()
+            .expect("NsmCertChain always contains an intermediate")
+    }
 
-        let (int_key, int_public_key) = generate_key();
-        let int_cert = build_cert(
-            Profile::SubCA {
-                issuer: Default::default(),
-                path_len_constraint: None,
+    /// Issues a new end certificate and signing key, signed by the intermediate that signed [`NsmCertChain::end_signer`].
+    /// The new certificate is valid for `valid_until`, starting now, and chains up to the existing root certificate.
+    /// It shares the end certificate's subject but is given a new random serial number.
+    ///
+    /// Useful to simulate many enclaves without changing the root certificate that clients have pinned.
+    /// Fails with [`ErrorKind::MissingSigningKey`] if the chain doesn't include the intermediate's signing key.
+    pub fn issue_leaf(&self, valid_until: Duration) -> Result<CertificateSigner, Error> {
+        let int_signer = self.int_signer();
+        builder::issue_leaf(
+            &int_signer.cert,
+            int_signer.require_signing_key()?,
+            self.end_signer.cert.tbs_certificate.subject.clone(),
+            None,
+            builder::validity(SystemTime::now(), valid_until)?,
+            &[],
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Generates a CRL, signed by the intermediate that signed the end certificate, that revokes [`NsmCertChain::end_signer`]'s certificate.
+    /// The CRL is issued now and its `nextUpdate` is the end certificate's `notAfter`.
+    ///
+    /// Provide its DER encoding to the verifier's CRLs to check that attestation documents signed by a revoked end certificate are rejected.
+    /// Fails with [`ErrorKind::MissingSigningKey`] if the chain doesn't include the intermediate's signing key.
+    pub fn generate_crl(&self) -> Result<CertificateList, Error> {
+        let not_before = x509_cert::time::Time::try_from(SystemTime::now())
+            .map_err(|err| Error::new(ErrorKind::Validity, err))?;
+
+        let int_signer = self.int_signer();
+        builder::issue_crl(
+            &int_signer.cert,
+            int_signer.require_signing_key()?,
+            &[self.end_signer.cert.tbs_certificate.serial_number.clone()],
+            x509_cert::time::Validity {
+                not_before,
+                not_after: self.end_signer.cert.tbs_certificate.validity.not_after,
             },
-            root_signing_key,
-            int_public_key,
-            valid_until,
+        )
+    }
+
+    /// Parses the JSON printed by the keygen CLI, in any of its `pem`, `der`, or `base64` formats.
+    /// The format is detected from the encoding of `rootCertificate`.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let value: serde_json::Value =
+            serde_json::from_str(json).map_err(|err| Error::new(ErrorKind::Encoding, err))?;
+
+        let chain = match &value["rootCertificate"] {
+            serde_json::Value::Array(_) => {
+                serde_json::from_value::<encode::der::DerNsmCertChain>(value).map(|chain| chain.0)
+            }
+            serde_json::Value::String(cert) if cert.trim_start().starts_with("-----BEGIN") => {
+                serde_json::from_value::<encode::pem::PemNsmCertChain>(value).map(|chain| chain.0)
+            }
+            _ => serde_json::from_value::<encode::base64::Base64NsmCertChain>(value)
+                .map(|chain| chain.0),
+        };
+
+        chain.map_err(|err| Error::new(ErrorKind::Encoding, err))
+    }
+
+    /// Creates a [`DevNitroBuilder`] that signs attestation documents with the end signing key.
+    /// The end certificate is used as the attestation document's `certificate`, and the intermediates as its `cabundle`.
+    /// Documents produced by the built [`DevNitro`] verify against [`NsmCertChain::root_signer`]'s certificate.
+    ///
+    /// Fails with [`ErrorKind::KeyAlgorithm`] if the end signing key isn't [`KeyAlgorithm::P384`], as attestation documents are always signed with P-384.
+    pub fn dev_nitro_builder(&self) -> Result<DevNitroBuilder, Error> {
+        let signing_key = self.end_signer.signing_key.as_p384().ok_or_else(|| {
+            Error::new(
+                ErrorKind::KeyAlgorithm,
+                ErrorContext("The end signing key must be P-384 to sign attestation documents"),
+            )
+        })?;
+        let end_cert = self
+            .end_signer
+            .cert
+            .to_der()
+            .map_err(|err| Error::new(ErrorKind::Encoding, err))?;
+        let ca_bundle = self
+            .int_certs()
+            .iter()
+            .map(|cert| cert.to_der().map(ByteBuf::from))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| Error::new(ErrorKind::Encoding, err))?;
+
+        Ok(DevNitro::builder(
+            p384::SecretKey::from(signing_key.as_nonzero_scalar()),
+            ByteBuf::from(end_cert),
+        )
+        .ca_bundle(ca_bundle))
+    }
+
+    /// Like [`NsmCertChain::dev_nitro_builder`], with the built [`DevNitro`] stamping attestation documents with `get_timestamp`.
+    /// Pass the same [`Time`] to the verifier to test freshness or certificate expiry at a fixed instant.
+    pub fn dev_nitro_builder_at(&self, get_timestamp: Time) -> Result<DevNitroBuilder, Error> {
+        Ok(self.dev_nitro_builder()?.get_timestamp(get_timestamp))
+    }
+
+    /// Signs a throwaway attestation document with the end signing key and verifies it against this chain's own root certificate.
+    /// Catches chains that can't be verified, i.e. a misconfigured certificate profile or issuer, at generation time rather than when a client verifies an attestation document.
+    ///
+    /// Fails with:
+    /// - [`ErrorKind::KeyAlgorithm`] if the end signing key isn't [`KeyAlgorithm::P384`], as attestation documents are always signed with P-384.
+    /// - [`ErrorKind::Attestation`] if [`DevNitro`] responds with an [`ErrorCode`] instead of an attestation document.
+    /// - [`ErrorKind::Verification`] if the attestation document doesn't verify, see [`Error::verify_error`] for the [`VerifyError`].
+    pub fn self_verify(&self) -> Result<(), Error> {
+        let nsm = self.dev_nitro_builder()?.build();
+        let root_cert = self
+            .root_signer
+            .cert
+            .to_der()
+            .map_err(|err| Error::new(ErrorKind::Encoding, err))?;
+
+        let document = match nsm.process_request(Request::Attestation {
+            user_data: None,
+            nonce: None,
+            public_key: None,
+        }) {
+            Response::Attestation { document } => document,
+            Response::Error(error_code) => {
+                return Err(Error::new(ErrorKind::Attestation, NsmErrorCode(error_code)))
+            }
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::Attestation,
+                    NsmErrorCode(ErrorCode::InvalidResponse),
+                ))
+            }
+        };
+
+        AttestationDoc::from_cose(&document, &root_cert, Time::default())
+            .map_err(|err| Error::new(ErrorKind::Verification, err))?;
+
+        Ok(())
+    }
+
+    /// Serializes this chain with each of the [`encode`] serializers, deserializes it back, and asserts that every certificate and signing key is unchanged.
+    /// Intended for tests, i.e. to check that stored chains still load after upgrading this crate.
+    ///
+    /// Panics if the chain fails to serialize or deserialize, or doesn't round trip.
+    pub fn assert_round_trips(&self) {
+        let expected = self.to_der_parts();
+
+        let json = serde_json::to_string(&encode::der::DerNsmCertChain(self.clone()))
+            .expect("Failed to serialize DER encoded NsmCertChain");
+        let der: encode::der::DerNsmCertChain =
+            serde_json::from_str(&json).expect("Failed to deserialize DER encoded NsmCertChain");
+        // `assert_eq!` would print the signing keys on failure
+        assert!(
+            der.0.to_der_parts() == expected,
+            "NsmCertChain didn't round trip through DER encoding"
         );
 
-        let (end_signing_key, end_public_key) = generate_key();
-        let end_cert = build_cert(
-            Profile::Leaf {
-                issuer: Default::default(),
-                enable_key_agreement: false,
-                enable_key_encipherment: false,
-            },
-            int_key,
-            end_public_key,
-            valid_until,
+        let json = serde_json::to_string(&encode::pem::PemNsmCertChain(self.clone()))
+            .expect("Failed to serialize PEM encoded NsmCertChain");
+        let pem: encode::pem::PemNsmCertChain =
+            serde_json::from_str(&json).expect("Failed to deserialize PEM encoded NsmCertChain");
+        assert!(
+            pem.0.to_der_parts() == expected,
+            "NsmCertChain didn't round trip through PEM encoding"
         );
 
+        let json = serde_json::to_string(&encode::base64::Base64NsmCertChain(self.clone()))
+            .expect("Failed to serialize base64 encoded NsmCertChain");
+        let base64: encode::base64::Base64NsmCertChain =
+            serde_json::from_str(&json).expect("Failed to deserialize base64 encoded NsmCertChain");
+        assert!(
+            base64.0.to_der_parts() == expected,
+            "NsmCertChain didn't round trip through base64 encoding"
+        );
+    }
+
+    /// Every certificate and PKCS#8 signing key, DER encoded, ordered from the root to the end signer. Missing signing keys are empty.
+    fn to_der_parts(&self) -> Vec<Vec<u8>> {
+        std::iter::once(&self.root_signer)
+            .chain(&self.int_signers)
+            .map(|issuer| (&issuer.cert, issuer.signing_key.as_ref()))
+            .chain([(&self.end_signer.cert, Some(&self.end_signer.signing_key))])
+            .flat_map(|(cert, signing_key)| {
+                [
+                    cert.to_der().expect("Failed to DER encode certificate"),
+                    signing_key
+                        .map(|signing_key| {
+                            signing_key
+                                .to_pkcs8_der()
+                                .expect("Failed to DER encode signing key")
+                                .as_bytes()
+                                .to_vec()
+                        })
+                        .unwrap_or_default(),
+                ]
+            })
+            .collect()
+    }
+
+    /// Generates an [`NsmCertChain`] that is valid until the specified [`Duration`].
+    /// Each certificate is given a distinct subject: [`DEFAULT_ROOT_SUBJECT`], [`DEFAULT_INT_SUBJECT`], and [`DEFAULT_END_SUBJECT`].
+    ///
+    /// These functions are not designed to be called inside a server and will panic if the chain can't be generated, i.e. when `valid_until` is zero.
+    /// Use [`NsmCertChain::builder`] to handle those errors.
+    pub fn generate(valid_until: Duration) -> Self {
+        Self::builder(valid_until)
+            .build()
+            .expect("Failed to generate NsmCertChain")
+    }
+
+    /// Generates an [`NsmCertChain`] that is valid until the specified [`Duration`], deriving every key and serial number from `seed`.
+    /// The certificates are valid starting now, use [`NsmCertChainBuilder::seed`] along with [`NsmCertChainBuilder::not_before`] to generate byte-identical chains.
+    ///
+    /// These functions are not designed to be called inside a server and will panic if the chain can't be generated, i.e. when `valid_until` is zero.
+    /// Use [`NsmCertChain::builder`] to handle those errors.
+    pub fn generate_seeded(valid_until: Duration, seed: [u8; 32]) -> Self {
+        Self::builder(valid_until)
+            .seed(seed)
+            .build()
+            .expect("Failed to generate NsmCertChain")
+    }
+
+    /// Generates an [`NsmCertChain`] that has already expired: it was valid from two hours ago until one hour ago.
+    /// Useful for testing that verifiers reject stale attestation documents.
+    /// Use [`NsmCertChainBuilder::not_before`] to control the exact validity window.
+    ///
+    /// These functions are not designed to be called inside a server and will panic if the chain can't be generated.
+    pub fn generate_expired() -> Self {
+        let hour = Duration::from_secs(60 * 60);
+        Self::builder(hour)
+            .not_before(SystemTime::now() - hour * 2)
+            .build()
+            .expect("Failed to generate NsmCertChain")
+    }
+
+    /// Generates an [`NsmCertChain`] that is valid until the specified [`Duration`], using the provided subjects.
+    /// The issuer of the intermediate and end certificates is set to the subject of their parent, keeping the chain internally consistent.
+    /// A [`Name`] can be parsed from an RFC 4514 string, i.e. `"CN=dev-root".parse::<Name>()`.
+    ///
+    /// These functions are not designed to be called inside a server and will panic if the chain can't be generated, i.e. when `valid_until` is zero.
+    /// Use [`NsmCertChain::builder`] to handle those errors.
+    pub fn generate_with_subjects(
+        valid_until: Duration,
+        root_subject: Name,
+        int_subject: Name,
+        end_subject: Name,
+    ) -> Self {
+        Self::builder(valid_until)
+            .root_subject(root_subject)
+            .int_subject(int_subject)
+            .end_subject(end_subject)
+            .build()
+            .expect("Failed to generate NsmCertChain")
+    }
+}
+
+/// Captures errors that can occur while generating an [`NsmCertChain`].
+/// `kind` is a high-level categorization of the error.
+/// `source` is the underlying error that caused the failure, or an [`ErrorContext`] if the error originated due to this library's own assertions.
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    source: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl Error {
+    fn new<E>(kind: ErrorKind, err: E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
         Self {
-            root: root_cert,
-            int: int_cert,
-            end_signer: EndCertificateSigner {
-                cert: end_cert,
-                signing_key: end_signing_key,
-            },
+            kind,
+            source: Box::new(err),
         }
     }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// The [`VerifyError`] behind an [`ErrorKind::Verification`] error, to inspect why the attestation document didn't verify
+    pub fn verify_error(&self) -> Option<&VerifyError> {
+        self.source.downcast_ref()
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}", self.kind, self.source)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub enum ErrorKind {
+    Validity,
+    Random,
+    IntermediateCount,
+    SerialNumber,
+    PublicKey,
+    Certificate,
+    Encoding,
+    KeyAlgorithm,
+    Crl,
+    /// The chain doesn't include the signing key of the certificate that would issue the requested certificate or CRL
+    MissingSigningKey,
+    /// [`DevNitro`] didn't respond with an attestation document, the source is the [`NsmErrorCode`] it responded with
+    Attestation,
+    /// An attestation document signed by the chain didn't verify against its own root certificate, see [`Error::verify_error`]
+    Verification,
 }
 
-fn build_cert(
-    profile: Profile,
-    signing_key: SigningKey,
-    public_key: VerifyingKey,
-    valid_until: Duration,
-) -> Certificate {
-    let cert = CertificateBuilder::new(
-        profile.clone(),
-        SerialNumber::new(&[1]).expect("SerialNumber"),
-        Validity::from_now(valid_until).expect("Validity"),
-        Name::default(),
-        SubjectPublicKeyInfo::from_key(public_key).expect("SubjectPublicKeyInfo"),
-        &signing_key,
-    )
-    .unwrap()
-    .build::<DerSignature>()
-    .unwrap();
-
-    cert
+/// The [`ErrorCode`] a Nitro Secure Module responded with, as an error source
+#[derive(Debug)]
+pub struct NsmErrorCode(pub ErrorCode);
+
+impl std::fmt::Display for NsmErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Nitro Secure Module responded with {:?}", self.0)
+    }
 }
 
-fn generate_key() -> (SigningKey, VerifyingKey) {
-    let signing_key =
-        SigningKey::try_from(SecretKey::random(&mut rand::thread_rng())).expect("SigningKey");
-    let verifying_key = VerifyingKey::from(signing_key.clone());
-    (signing_key, verifying_key)
+impl std::error::Error for NsmErrorCode {}
+
+/// Used by errors to provide additional context if the error originated due to this library's own assertions.
+#[derive(Debug, PartialEq, Eq, Clone, Hash, PartialOrd, Ord)]
+pub struct ErrorContext(pub(crate) &'static str);
+
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
 
+impl std::error::Error for ErrorContext {}
+
 #[cfg(test)]
 mod test {
-    use crate::NsmCertChain;
-    use std::time::Duration;
+    use crate::{
+        Certificate, DerDecodeExt, DerEncodeExt, ErrorKind, KeyAlgorithm, NsmCertChain,
+        DEFAULT_END_SUBJECT, DEFAULT_INT_SUBJECT, DEFAULT_ROOT_SUBJECT,
+    };
+    use nsm_nitro_enclave_utils::{
+        api::nsm::AttestationDoc,
+        time::Time,
+        verify::{AttestationDocVerifierExt, ErrorKind as VerifyErrorKind},
+    };
+    use std::time::{Duration, SystemTime};
 
     #[test]
     fn generate_chain() {
-        let until = Duration::from_secs(0);
+        let until = Duration::from_secs(1);
         NsmCertChain::generate(until);
     }
+
+    #[test]
+    fn round_trips() {
+        NsmCertChain::builder(Duration::from_secs(1))
+            .intermediate_count(2)
+            .build()
+            .unwrap()
+            .assert_round_trips();
+        NsmCertChain::builder(Duration::from_secs(1))
+            .key_algorithm(KeyAlgorithm::P256)
+            .build()
+            .unwrap()
+            .assert_round_trips();
+    }
+
+    #[test]
+    fn from_json() {
+        let chain = NsmCertChain::generate(Duration::from_secs(60));
+        let json = [
+            serde_json::to_string(&crate::encode::der::DerNsmCertChain(chain.clone())).unwrap(),
+            serde_json::to_string(&crate::encode::pem::PemNsmCertChain(chain.clone())).unwrap(),
+            serde_json::to_string(&crate::encode::base64::Base64NsmCertChain(chain.clone()))
+                .unwrap(),
+        ];
+
+        for json in json {
+            let parsed = NsmCertChain::from_json(&json).unwrap();
+            assert!(parsed.to_der_parts() == chain.to_der_parts());
+        }
+
+        let err = NsmCertChain::from_json("{}").err().unwrap();
+        assert_eq!(err.kind(), &ErrorKind::Encoding);
+    }
+
+    #[test]
+    fn missing_issuer_signing_keys() {
+        let mut chain = NsmCertChain::generate(Duration::from_secs(60));
+        chain.root_signer.signing_key = None;
+        for int_signer in &mut chain.int_signers {
+            int_signer.signing_key = None;
+        }
+
+        let json =
+            serde_json::to_value(crate::encode::pem::PemNsmCertChain(chain.clone())).unwrap();
+        assert!(json.get("rootSigningKey").is_none());
+        assert!(json.get("intSigningKeys").is_none());
+        let parsed = NsmCertChain::from_json(&json.to_string()).unwrap();
+        assert!(parsed.root_signer.signing_key.is_none());
+        assert!(parsed.int_signer().signing_key.is_none());
+        parsed.assert_round_trips();
+
+        // The end signer is enough to sign attestation documents, but not to issue certificates
+        parsed.dev_nitro_builder().unwrap().build();
+        let err = parsed.issue_leaf(Duration::from_secs(60)).err().unwrap();
+        assert_eq!(err.kind(), &ErrorKind::MissingSigningKey);
+        let err = parsed.generate_crl().err().unwrap();
+        assert_eq!(err.kind(), &ErrorKind::MissingSigningKey);
+    }
+
+    #[test]
+    fn legacy_bundles() {
+        let chain = NsmCertChain::generate(Duration::from_secs(60));
+        let json = [
+            serde_json::to_value(crate::encode::der::DerNsmCertChain(chain.clone())).unwrap(),
+            serde_json::to_value(crate::encode::pem::PemNsmCertChain(chain.clone())).unwrap(),
+            serde_json::to_value(crate::encode::base64::Base64NsmCertChain(chain.clone())).unwrap(),
+        ];
+
+        for mut json in json {
+            // Earlier versions of the keygen CLI wrote a single `intCertificate`, and no root or intermediate signing keys
+            let json = json.as_object_mut().unwrap();
+            let int_certificate = json.remove("intCertificates").unwrap()[0].take();
+            json.insert("intCertificate".to_string(), int_certificate);
+            json.remove("rootSigningKey");
+            json.remove("intSigningKeys");
+
+            let parsed =
+                NsmCertChain::from_json(&serde_json::Value::from(json.clone()).to_string())
+                    .unwrap();
+            assert_eq!(parsed.int_signers().len(), 1);
+            assert_eq!(
+                parsed.int_signer().cert.to_der().unwrap(),
+                chain.int_signer().cert.to_der().unwrap()
+            );
+            assert_eq!(
+                parsed.end_signer.cert.to_der().unwrap(),
+                chain.end_signer.cert.to_der().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn new() {
+        let chain = NsmCertChain::generate(Duration::from_secs(60));
+        let rebuilt = NsmCertChain::new(
+            chain.root_signer.clone(),
+            chain.int_signers().to_vec(),
+            chain.end_signer.clone(),
+        )
+        .unwrap();
+        assert!(rebuilt.to_der_parts() == chain.to_der_parts());
+
+        let err = NsmCertChain::new(chain.root_signer, Vec::new(), chain.end_signer)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), &ErrorKind::IntermediateCount);
+    }
+
+    #[test]
+    fn dev_nitro_builder() {
+        let chain = NsmCertChain::generate(Duration::from_secs(60));
+        let nsm = chain.dev_nitro_builder().unwrap().build();
+        let document = nsm.attest(None, None, None).unwrap();
+        let root_cert = chain.root_signer.cert.to_der().unwrap();
+        AttestationDoc::from_cose(&document, &root_cert, Time::default()).unwrap();
+
+        let chain = NsmCertChain::builder(Duration::from_secs(60))
+            .key_algorithm(KeyAlgorithm::P256)
+            .build()
+            .unwrap();
+        let err = chain.dev_nitro_builder().err().unwrap();
+        assert_eq!(err.kind(), &ErrorKind::KeyAlgorithm);
+    }
+
+    #[test]
+    fn dev_nitro_builder_at() {
+        let chain = NsmCertChain::generate(Duration::from_secs(60));
+        let now = Time::default().time();
+        let nsm = chain
+            .dev_nitro_builder_at(Time::fixed(now))
+            .unwrap()
+            .build();
+        let document = nsm.attest(None, None, None).unwrap();
+        let root_cert = chain.root_signer.cert.to_der().unwrap();
+        let doc = AttestationDoc::from_cose(&document, &root_cert, Time::fixed(now)).unwrap();
+        assert_eq!(doc.timestamp, now);
+    }
+
+    #[test]
+    fn generate_crl() {
+        use nsm_nitro_enclave_utils::verify::AttestationVerifier;
+
+        for key_algorithm in [KeyAlgorithm::P384, KeyAlgorithm::P256] {
+            let chain = NsmCertChain::builder(Duration::from_secs(60))
+                .key_algorithm(key_algorithm)
+                .build()
+                .unwrap();
+            let crl = chain.generate_crl().unwrap();
+            assert_eq!(
+                crl.tbs_cert_list.issuer,
+                chain.int_signer().cert.tbs_certificate.subject
+            );
+            assert_eq!(
+                crl.tbs_cert_list.revoked_certificates.unwrap()[0].serial_number,
+                chain.end_signer.cert.tbs_certificate.serial_number
+            );
+        }
+
+        let chain = NsmCertChain::generate(Duration::from_secs(60));
+        let document = chain
+            .dev_nitro_builder()
+            .unwrap()
+            .build()
+            .attest(None, None, None)
+            .unwrap();
+        let err = AttestationVerifier::new(chain.root_signer.cert.to_der().unwrap())
+            .crls(vec![chain.generate_crl().unwrap().to_der().unwrap()])
+            .verify(&document)
+            .unwrap_err();
+        assert_eq!(err.kind(), &VerifyErrorKind::CertificateRevoked);
+    }
+
+    #[test]
+    fn fingerprint_sha384() {
+        let root_cert =
+            Certificate::from_der(include_bytes!("../../test_data/root-certificate.der")).unwrap();
+        // openssl dgst -sha384 test_data/root-certificate.der
+        assert_eq!(
+            super::fingerprint_sha384(&root_cert),
+            "1695aeef88f9397127d5f72454b3908a27a8ebc73be79297b241b2f4e7d154e459faecc5bdebad99533332aab46fff01"
+        );
+    }
+
+    #[test]
+    fn distinct_subjects() {
+        let chain = NsmCertChain::generate(Duration::from_secs(1));
+
+        let root = &chain.root_signer.cert.tbs_certificate;
+        let int = &chain.int_signer().cert.tbs_certificate;
+        let end = &chain.end_signer.cert.tbs_certificate;
+
+        assert_eq!(root.subject.to_string(), DEFAULT_ROOT_SUBJECT);
+        assert_eq!(int.subject.to_string(), DEFAULT_INT_SUBJECT);
+        assert_eq!(end.subject.to_string(), DEFAULT_END_SUBJECT);
+
+        assert_eq!(root.issuer, root.subject);
+        assert_eq!(int.issuer, root.subject);
+        assert_eq!(end.issuer, int.subject);
+    }
+
+    #[test]
+    fn custom_subjects() {
+        let chain = NsmCertChain::generate_with_subjects(
+            Duration::from_secs(1),
+            "CN=custom-root".parse().unwrap(),
+            "CN=custom-int".parse().unwrap(),
+            "CN=custom-end".parse().unwrap(),
+        );
+
+        assert_eq!(
+            chain.root_signer.cert.tbs_certificate.subject.to_string(),
+            "CN=custom-root"
+        );
+        assert_eq!(
+            chain.int_signer().cert.tbs_certificate.subject.to_string(),
+            "CN=custom-int"
+        );
+        assert_eq!(
+            chain.end_signer.cert.tbs_certificate.subject.to_string(),
+            "CN=custom-end"
+        );
+    }
+
+    #[test]
+    fn signing_keys_match_certificates() {
+        let chain = NsmCertChain::generate(Duration::from_secs(1));
+
+        for (cert, signing_key) in [
+            (
+                &chain.root_signer.cert,
+                chain.root_signer.signing_key.as_ref(),
+            ),
+            (
+                &chain.int_signer().cert,
+                chain.int_signer().signing_key.as_ref(),
+            ),
+            (&chain.end_signer.cert, Some(&chain.end_signer.signing_key)),
+        ] {
+            assert_eq!(
+                signing_key.unwrap().public_key_info().unwrap(),
+                cert.tbs_certificate.subject_public_key_info
+            );
+        }
+    }
+
+    #[test]
+    fn issue_leaf() {
+        let chain = NsmCertChain::generate(Duration::from_secs(60));
+
+        let first = chain.issue_leaf(Duration::from_secs(60)).unwrap();
+        let second = chain.issue_leaf(Duration::from_secs(60)).unwrap();
+
+        for leaf in [&first, &second] {
+            let tbs = &leaf.cert.tbs_certificate;
+            assert_eq!(tbs.issuer, chain.int_signer().cert.tbs_certificate.subject);
+            assert_eq!(
+                leaf.signing_key.public_key_info().unwrap(),
+                tbs.subject_public_key_info
+            );
+        }
+        assert_ne!(
+            first.cert.tbs_certificate.serial_number,
+            second.cert.tbs_certificate.serial_number
+        );
+        assert_ne!(
+            first.cert.tbs_certificate.subject_public_key_info,
+            second.cert.tbs_certificate.subject_public_key_info
+        );
+    }
+
+    #[test]
+    fn generate_expired() {
+        let chain = NsmCertChain::generate_expired();
+        let now = SystemTime::now();
+
+        for cert in [
+            &chain.root_signer.cert,
+            &chain.int_signer().cert,
+            &chain.end_signer.cert,
+        ] {
+            assert!(cert.tbs_certificate.validity.not_after.to_system_time() < now);
+        }
+    }
+
+    #[test]
+    fn self_verify() {
+        NsmCertChain::generate(Duration::from_secs(60))
+            .self_verify()
+            .unwrap();
+        NsmCertChain::builder(Duration::from_secs(60))
+            .intermediate_count(3)
+            .build()
+            .unwrap()
+            .self_verify()
+            .unwrap();
+    }
+
+    #[test]
+    fn self_verify_rejects_unverifiable_chains() {
+        let err = NsmCertChain::generate_expired().self_verify().unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::Verification);
+        assert_eq!(
+            err.verify_error().unwrap().kind(),
+            &VerifyErrorKind::CertificateExpired
+        );
+
+        let err = NsmCertChain::builder(Duration::from_secs(60))
+            .key_algorithm(KeyAlgorithm::P256)
+            .build()
+            .unwrap()
+            .self_verify()
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::KeyAlgorithm);
+        assert!(err.verify_error().is_none());
+    }
 }
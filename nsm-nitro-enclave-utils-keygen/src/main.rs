@@ -1,8 +1,13 @@
-use clap::{Parser, ValueEnum};
+use clap::{CommandFactory, Parser, ValueEnum};
 use nsm_nitro_enclave_utils_keygen::{
-    encode::der::DerNsmCertChain, encode::pem::PemNsmCertChain, NsmCertChain,
+    encode::base64::Base64NsmCertChain,
+    encode::der::DerNsmCertChain,
+    encode::pem::{pem_bundle, PemNsmCertChain},
+    encode::pkcs12::pkcs12_encode,
+    NsmCertChain,
 };
 use std::fs;
+use std::io::Write;
 use std::path::PathBuf;
 use std::time::Duration;
 
@@ -18,44 +23,146 @@ struct Args {
     format: Format,
     #[arg(
         long,
-        default_value = "365",
-        help = "The amount of days the generated certificates will be valid for."
+        help = "The amount of days the generated certificates will be valid for. Added to --hours and --minutes. If none of these are provided, the certificates are valid for 365 days."
     )]
-    days: u64,
+    days: Option<u64>,
+    #[arg(
+        long,
+        help = "The amount of hours the generated certificates will be valid for. Added to --days and --minutes."
+    )]
+    hours: Option<u64>,
+    #[arg(
+        long,
+        help = "The amount of minutes the generated certificates will be valid for. Added to --days and --hours."
+    )]
+    minutes: Option<u64>,
+    #[arg(
+        long,
+        default_value = "1",
+        help = "The amount of intermediate certificates between the root and end certificate."
+    )]
+    intermediates: usize,
     #[arg(
         long,
         help = "The directory where the generated certificates will be written to. If omitted, the generated certificates will be sent to stdout."
     )]
     dir: Option<PathBuf>,
+    #[arg(
+        long,
+        default_value = "json",
+        help = "How the generated certificates are sent to stdout when --dir is omitted. Only used by the pem, der and base64 formats."
+    )]
+    stdout_format: StdoutFormat,
+    #[arg(
+        long,
+        default_value = "",
+        help = "The password protecting the PKCS#12 archive. Only used by the pkcs12 format."
+    )]
+    password: String,
 }
 
 #[derive(ValueEnum, Clone, Debug)]
 enum Format {
     Pem,
     Der,
+    /// DER, encoded with the standard, padded, base64 alphabet. Useful for embedding the certificates in text based configuration.
+    Base64,
+    /// A single PEM file, ordered leaf-first: the end certificate, the intermediates, the root certificate, and finally the end signing key.
+    PemBundle,
+    /// A password-protected PKCS#12 archive containing the end signing key and the certificate chain, ordered leaf-first.
+    Pkcs12,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum StdoutFormat {
+    /// A JSON object containing every certificate and signing key
+    Json,
+    /// The certificates and signing keys, one after the other, ordered like the files written to --dir. Not supported by the der format.
+    Raw,
+}
+
+impl Args {
+    /// The combined duration of `days`, `hours` and `minutes`, defaulting to 365 days when none are provided.
+    fn valid_until(&self) -> Duration {
+        const MINUTE: u64 = 60;
+        const HOUR: u64 = 60 * MINUTE;
+        const DAY: u64 = 24 * HOUR;
+
+        if self.days.is_none() && self.hours.is_none() && self.minutes.is_none() {
+            return Duration::from_secs(365 * DAY);
+        }
+
+        Duration::from_secs(
+            self.days.unwrap_or_default() * DAY
+                + self.hours.unwrap_or_default() * HOUR
+                + self.minutes.unwrap_or_default() * MINUTE,
+        )
+    }
 }
 
 impl Format {
     fn as_file_extension(&self) -> &'static str {
         match self {
-            Format::Pem => "pem",
+            Format::Pem | Format::PemBundle => "pem",
             Format::Der => "der",
+            Format::Base64 => "b64",
+            Format::Pkcs12 => "p12",
         }
     }
 }
 
 fn main() {
     let args = Args::parse();
+    if matches!(args.format, Format::Der)
+        && matches!(args.stdout_format, StdoutFormat::Raw)
+        && args.dir.is_none()
+    {
+        Args::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--stdout-format raw can't be used with the der format",
+            )
+            .exit();
+    }
 
-    let duration = Duration::from_secs(60 * 60 * 24 * args.days);
-    let cert_chain = NsmCertChain::generate(duration);
+    let cert_chain = NsmCertChain::builder(args.valid_until())
+        .intermediate_count(args.intermediates)
+        .build()
+        .expect("Failed to generate certificates");
 
     let json = match args.format {
+        Format::PemBundle => {
+            let bundle = pem_bundle(&cert_chain).expect("Failed to encode bundle");
+            match args.dir {
+                Some(mut path) => {
+                    path.push("bundle.pem");
+                    write_private(path, bundle).expect("Failed to write bundle");
+                }
+                None => print!("{bundle}"),
+            }
+            return;
+        }
+        Format::Pkcs12 => {
+            let p12 = pkcs12_encode(&cert_chain, &args.password).expect("Failed to encode PKCS#12");
+            match args.dir {
+                Some(mut path) => {
+                    path.push(format!("end-signer.{}", args.format.as_file_extension()));
+                    write_private(path, p12).expect("Failed to write PKCS#12");
+                }
+                None => std::io::stdout()
+                    .write_all(&p12)
+                    .expect("Failed to write PKCS#12"),
+            }
+            return;
+        }
         Format::Pem => {
-            serde_json::to_value(&PemNsmCertChain(cert_chain)).expect("Failed to serialize")
+            serde_json::to_value(PemNsmCertChain(cert_chain)).expect("Failed to serialize")
         }
         Format::Der => {
-            serde_json::to_value(&DerNsmCertChain(cert_chain)).expect("Failed to serialize")
+            serde_json::to_value(DerNsmCertChain(cert_chain)).expect("Failed to serialize")
+        }
+        Format::Base64 => {
+            serde_json::to_value(Base64NsmCertChain(cert_chain)).expect("Failed to serialize")
         }
     };
 
@@ -64,27 +171,110 @@ fn main() {
 
         // Push the first filename, use `set_file_name` for everything else.
         path.push(format!("root-certificate.{file_extension}"));
-        let root: Vec<u8> = serde_json::from_value(json["rootCertificate"].to_owned())
-            .expect("Invalid certificate");
+        let root = file_contents(&json["rootCertificate"]);
         fs::write(path.clone(), root).expect("Failed to write root certificate");
 
-        path.set_file_name(format!("int-certificate.{file_extension}"));
-        let int: Vec<u8> =
-            serde_json::from_value(json["intCertificate"].to_owned()).expect("Invalid certificate");
-        fs::write(path.clone(), int).expect("Failed to write int certificate");
+        path.set_file_name(format!("root-signing-key.{file_extension}"));
+        let root_signing_key = file_contents(&json["rootSigningKey"]);
+        write_private(path.clone(), root_signing_key).expect("Failed to write root signing key");
+
+        let int_certs = files_contents(&json["intCertificates"]);
+        let int_signing_keys = files_contents(&json["intSigningKeys"]);
+        // A single intermediate keeps the unnumbered file names, more are numbered starting at the one signed by the root.
+        let int_file_suffix = |index: usize| match int_certs.len() {
+            1 => String::new(),
+            _ => format!("-{}", index + 1),
+        };
+        for (index, (int, int_signing_key)) in int_certs.iter().zip(&int_signing_keys).enumerate() {
+            let suffix = int_file_suffix(index);
+
+            path.set_file_name(format!("int-certificate{suffix}.{file_extension}"));
+            fs::write(path.clone(), int).expect("Failed to write int certificate");
+
+            path.set_file_name(format!("int-signing-key{suffix}.{file_extension}"));
+            write_private(path.clone(), int_signing_key).expect("Failed to write int signing key");
+        }
 
         path.set_file_name(format!("end-certificate.{file_extension}"));
-        let end_certificate: Vec<u8> =
-            serde_json::from_value(json["endCertificate"].to_owned()).expect("Invalid certificate");
+        let end_certificate = file_contents(&json["endCertificate"]);
         fs::write(path.clone(), end_certificate).expect("Failed to write end certificate");
 
         path.set_file_name(format!("end-signing-key.{file_extension}"));
-        let end_signing_key: Vec<u8> =
-            serde_json::from_value(json["endSigningKey"].to_owned()).expect("Invalid certificate");
-        fs::write(path, end_signing_key).expect("Failed to write end signing key");
+        let end_signing_key = file_contents(&json["endSigningKey"]);
+        write_private(path, end_signing_key).expect("Failed to write end signing key");
 
         return;
     }
 
-    println!("{json}");
+    match args.stdout_format {
+        StdoutFormat::Json => println!("{json}"),
+        StdoutFormat::Raw => {
+            let mut stdout = std::io::stdout();
+            let int_certs = files_contents(&json["intCertificates"]);
+            let int_signing_keys = files_contents(&json["intSigningKeys"]);
+            let contents = [
+                file_contents(&json["rootCertificate"]),
+                file_contents(&json["rootSigningKey"]),
+            ]
+            .into_iter()
+            .chain(
+                int_certs
+                    .into_iter()
+                    .zip(int_signing_keys)
+                    .flat_map(|(int, int_signing_key)| [int, int_signing_key]),
+            )
+            .chain([
+                file_contents(&json["endCertificate"]),
+                file_contents(&json["endSigningKey"]),
+            ]);
+
+            for mut content in contents {
+                // PEM blocks end with a newline, base64 strings are separated by one
+                if !content.ends_with(b"\n") {
+                    content.push(b'\n');
+                }
+                stdout
+                    .write_all(&content)
+                    .expect("Failed to write to stdout");
+            }
+        }
+    }
+}
+
+/// Behaves like [`fs::write`], restricting the file's permissions to its owner on Unix, as it contains a signing key
+fn write_private(path: PathBuf, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let mut file = options.open(path)?;
+        // `mode` only applies when the file is created, an existing file keeps its permissions
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        file.write_all(contents.as_ref())
+    }
+
+    #[cfg(not(unix))]
+    options.open(path)?.write_all(contents.as_ref())
+}
+
+/// The contents of the file a serialized certificate or signing key is written to.
+/// PEM and base64 encodings serialize to strings, DER encoding serializes to an array of bytes.
+fn file_contents(value: &serde_json::Value) -> Vec<u8> {
+    match value {
+        serde_json::Value::String(s) => s.clone().into_bytes(),
+        value => serde_json::from_value(value.to_owned()).expect("Invalid certificate"),
+    }
+}
+
+/// Behaves like [`file_contents`], for a list of serialized certificates or signing keys
+fn files_contents(value: &serde_json::Value) -> Vec<Vec<u8>> {
+    value
+        .as_array()
+        .expect("Invalid certificates")
+        .iter()
+        .map(file_contents)
+        .collect()
 }
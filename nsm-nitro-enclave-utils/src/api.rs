@@ -1,6 +1,12 @@
+pub use crate::attestation_doc::{AttestationDocBuilder, DisplayAttestationDoc};
+pub use crate::nonce::Nonce;
 pub use crate::time::Time;
 pub use serde_bytes::ByteBuf;
 
+/// The size of the Nitro Secure Module's response buffer, in bytes, which an authentic attestation document must fit in.
+/// The default limit of both `DevNitroBuilder::max_document_size` and `AttestationVerifier::max_document_size`.
+pub const MAX_DOCUMENT_SIZE: usize = 0x3000;
+
 pub mod nsm {
     pub use aws_nitro_enclaves_nsm_api::api::{
         AttestationDoc, Digest, ErrorCode, Request, Response,
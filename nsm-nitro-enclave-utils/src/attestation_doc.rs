@@ -0,0 +1,203 @@
+use crate::api::nsm::{AttestationDoc, Digest};
+use crate::api::ByteBuf;
+use crate::pcr::Pcrs;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+
+/// Builds an [`AttestationDoc`], filling every field that isn't explicitly set with a default.
+/// Useful for creating attestation documents to sign in tests.
+pub struct AttestationDocBuilder {
+    module_id: String,
+    digest: Option<Digest>,
+    timestamp: u64,
+    pcrs: Pcrs,
+    certificate: ByteBuf,
+    cabundle: Vec<ByteBuf>,
+    public_key: Option<ByteBuf>,
+    user_data: Option<ByteBuf>,
+    nonce: Option<ByteBuf>,
+}
+
+impl AttestationDocBuilder {
+    /// `certificate` a der encoded x509 certificate, containing the public key of the key that will sign the attestation document
+    pub fn new(certificate: ByteBuf) -> Self {
+        Self {
+            module_id: String::new(),
+            digest: None,
+            timestamp: 0,
+            pcrs: Pcrs::default(),
+            certificate,
+            cabundle: Vec::new(),
+            public_key: None,
+            user_data: None,
+            nonce: None,
+        }
+    }
+
+    /// Set the attestation document's `module_id`. Defaults to an empty string.
+    pub fn module_id(self, module_id: String) -> Self {
+        Self { module_id, ..self }
+    }
+
+    /// Set the attestation document's `digest`. Defaults to [`Digest::SHA384`], the only `digest` the verifier accepts, see [`VerifiedAttestationDoc::digest`](crate::verify::VerifiedAttestationDoc::digest).
+    pub fn digest(self, digest: Digest) -> Self {
+        Self {
+            digest: Some(digest),
+            ..self
+        }
+    }
+
+    /// Set the attestation document's `timestamp`, expressed as milliseconds since Unix Epoch. Defaults to 0.
+    pub fn timestamp(self, timestamp: u64) -> Self {
+        Self { timestamp, ..self }
+    }
+
+    /// Set the attestation document's PCRs. Defaults to [`Pcrs::default`].
+    pub fn pcrs(self, pcrs: Pcrs) -> Self {
+        Self { pcrs, ..self }
+    }
+
+    /// Set the attestation document's ca_bundle. Defaults to an empty list.
+    /// `cabundle` should be a list of der encoded intermediate certificates.
+    pub fn cabundle(self, cabundle: Vec<ByteBuf>) -> Self {
+        Self { cabundle, ..self }
+    }
+
+    pub fn public_key(self, public_key: ByteBuf) -> Self {
+        Self {
+            public_key: Some(public_key),
+            ..self
+        }
+    }
+
+    pub fn user_data(self, user_data: ByteBuf) -> Self {
+        Self {
+            user_data: Some(user_data),
+            ..self
+        }
+    }
+
+    pub fn nonce(self, nonce: ByteBuf) -> Self {
+        Self {
+            nonce: Some(nonce),
+            ..self
+        }
+    }
+
+    pub fn build(self) -> AttestationDoc {
+        AttestationDoc {
+            module_id: self.module_id,
+            digest: self.digest.unwrap_or(Digest::SHA384),
+            timestamp: self.timestamp,
+            pcrs: self.pcrs.into(),
+            certificate: self.certificate,
+            cabundle: self.cabundle,
+            public_key: self.public_key,
+            user_data: self.user_data,
+            nonce: self.nonce,
+        }
+    }
+}
+
+/// A wrapper for [`AttestationDoc`] that serializes its binary fields as strings, for logging and comparing attestation documents.
+/// PCRs are serialized like [`Pcrs`], a map of PCR index to hex string. `certificate` and `cabundle` are serialized as base64, the remaining binary fields as hex.
+/// Displays as pretty printed JSON.
+pub struct DisplayAttestationDoc(pub AttestationDoc);
+
+impl Serialize for DisplayAttestationDoc {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let doc = &self.0;
+        let mut s = serializer.serialize_struct("AttestationDoc", 9)?;
+
+        s.serialize_field("moduleId", &doc.module_id)?;
+        s.serialize_field("digest", &doc.digest)?;
+        s.serialize_field("timestamp", &doc.timestamp)?;
+        s.serialize_field("pcrs", &HexPcrs(&doc.pcrs))?;
+        s.serialize_field("certificate", &STANDARD.encode(&doc.certificate))?;
+        s.serialize_field(
+            "cabundle",
+            &doc.cabundle
+                .iter()
+                .map(|cert| STANDARD.encode(cert))
+                .collect::<Vec<String>>(),
+        )?;
+        s.serialize_field("publicKey", &doc.public_key.as_ref().map(hex::encode))?;
+        s.serialize_field("userData", &doc.user_data.as_ref().map(hex::encode))?;
+        s.serialize_field("nonce", &doc.nonce.as_ref().map(hex::encode))?;
+
+        s.end()
+    }
+}
+
+impl Display for DisplayAttestationDoc {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let json = serde_json::to_string_pretty(self).map_err(|_| std::fmt::Error)?;
+        write!(f, "{json}")
+    }
+}
+
+struct HexPcrs<'a>(&'a std::collections::BTreeMap<usize, ByteBuf>);
+
+impl Serialize for HexPcrs<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(self.0.iter().map(|(index, pcr)| (index, hex::encode(pcr))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AttestationDocBuilder, DisplayAttestationDoc};
+    use crate::api::{nsm::Digest, ByteBuf};
+    use crate::pcr::{PcrDigest, Pcrs};
+
+    #[test]
+    fn defaults() {
+        let doc = AttestationDocBuilder::new(ByteBuf::from(b"cert".to_vec())).build();
+        assert_eq!(doc.module_id, "");
+        assert_eq!(doc.digest, Digest::SHA384);
+        assert_eq!(doc.timestamp, 0);
+        assert_eq!(doc.pcrs, Pcrs::default().into());
+        assert_eq!(doc.certificate, b"cert");
+        assert!(doc.cabundle.is_empty());
+        assert!(doc.public_key.is_none() && doc.user_data.is_none() && doc.nonce.is_none());
+    }
+
+    #[test]
+    fn digest_defaults_to_sha384() {
+        let doc = AttestationDocBuilder::new(ByteBuf::new())
+            .pcrs(Pcrs::zeros_with_digest(PcrDigest::Sha256))
+            .build();
+        assert_eq!(doc.digest, Digest::SHA384);
+
+        let doc = AttestationDocBuilder::new(ByteBuf::new())
+            .pcrs(Pcrs::zeros_with_digest(PcrDigest::Sha256))
+            .digest(Digest::SHA512)
+            .build();
+        assert_eq!(doc.digest, Digest::SHA512);
+    }
+
+    #[test]
+    fn display_attestation_doc() {
+        let doc = AttestationDocBuilder::new(ByteBuf::from(b"cert".to_vec()))
+            .pcrs(Pcrs::zeros())
+            .cabundle(vec![ByteBuf::from(b"int".to_vec())])
+            .nonce(ByteBuf::from(b"nonce".to_vec()))
+            .build();
+
+        let json = serde_json::to_value(DisplayAttestationDoc(doc)).unwrap();
+        assert_eq!(json["moduleId"], "");
+        assert_eq!(json["digest"], "SHA384");
+        assert_eq!(json["pcrs"]["0"], "0".repeat(96));
+        assert_eq!(json["certificate"], "Y2VydA==");
+        assert_eq!(json["cabundle"], serde_json::json!(["aW50"]));
+        assert_eq!(json["publicKey"], serde_json::Value::Null);
+        assert_eq!(json["nonce"], "6e6f6e6365");
+    }
+}
@@ -0,0 +1,207 @@
+use crate::api::{
+    nsm::{ErrorCode, Request, Response},
+    ByteBuf,
+};
+use crate::driver::Driver;
+use std::future::Future;
+
+/// [`AsyncDriver`] is the async counterpart to [`Driver`], for Nitro Secure Modules that are reached asynchronously, i.e. proxied over vsock by another process.
+/// The returned future must be `Send`, allowing it to be awaited inside multi-threaded runtimes, like tokio.
+///
+/// An `async fn` can be used to implement it:
+/// ```rust
+/// use nsm_nitro_enclave_utils::{driver::AsyncDriver, api::nsm::{Request, Response, ErrorCode}};
+///
+/// struct Proxy;
+///
+/// impl AsyncDriver for Proxy {
+///     async fn process_request(&self, request: Request) -> Response {
+///         // Forward the request to the process that owns the Nitro Secure Module
+///         Response::Error(ErrorCode::InvalidOperation)
+///     }
+/// }
+/// ```
+pub trait AsyncDriver {
+    fn process_request(&self, request: Request) -> impl Future<Output = Response> + Send;
+}
+
+/// Adapts a [`Driver`] into an [`AsyncDriver`], allowing in-process drivers, like [`DevNitro`](crate::driver::dev::DevNitro), to be used where an [`AsyncDriver`] is expected.
+/// Requests are processed synchronously when the future is first polled, which is fine for drivers that don't block, like the in-process mocks.
+pub struct SyncDriver<D>(pub D);
+
+impl<D> AsyncDriver for SyncDriver<D>
+where
+    D: Driver + Sync,
+{
+    fn process_request(&self, request: Request) -> impl Future<Output = Response> + Send {
+        std::future::ready(self.0.process_request(request))
+    }
+}
+
+/// [`AsyncNsm`] is an async facade over an [`AsyncDriver`], turning the NSM's [`Response`]s into `Result`s that fail with the [`ErrorCode`] the Nitro Secure Module responded with,
+/// or [`ErrorCode::InvalidResponse`] if it responded to a different request.
+///
+/// Wrap a [`Driver`] in [`SyncDriver`] to use in-process drivers, like [`DevNitro`](crate::driver::dev::DevNitro), behind the same facade.
+/// ```rust
+/// use nsm_nitro_enclave_utils::{driver::{AsyncDriver, AsyncNsm}, api::nsm::{Request, Response, ErrorCode}};
+///
+/// struct Proxy;
+///
+/// impl AsyncDriver for Proxy {
+///     async fn process_request(&self, request: Request) -> Response {
+///         // Forward the request to the process that owns the Nitro Secure Module
+///         Response::Error(ErrorCode::InvalidOperation)
+///     }
+/// }
+///
+/// async fn attest(nsm: &AsyncNsm<Proxy>) -> Result<Vec<u8>, ErrorCode> {
+///     nsm.attestation(None, None, None).await
+/// }
+/// ```
+pub struct AsyncNsm<D> {
+    driver: D,
+}
+
+impl<D: AsyncDriver> AsyncNsm<D> {
+    pub fn new(driver: D) -> Self {
+        Self { driver }
+    }
+
+    /// The wrapped [`AsyncDriver`]
+    pub fn driver(&self) -> &D {
+        &self.driver
+    }
+
+    /// Sends `request` to the wrapped [`AsyncDriver`] as is
+    pub async fn process_request(&self, request: Request) -> Response {
+        self.driver.process_request(request).await
+    }
+
+    /// Requests an attestation document, returning its COSE bytes
+    pub async fn attestation(
+        &self,
+        user_data: Option<ByteBuf>,
+        nonce: Option<ByteBuf>,
+        public_key: Option<ByteBuf>,
+    ) -> Result<Vec<u8>, ErrorCode> {
+        let request = Request::Attestation {
+            user_data,
+            nonce,
+            public_key,
+        };
+        match self.process_request(request).await {
+            Response::Attestation { document } => Ok(document),
+            Response::Error(error) => Err(error),
+            _ => Err(ErrorCode::InvalidResponse),
+        }
+    }
+
+    /// Describes the PCR at `index`, returning whether it's locked and its value
+    pub async fn describe_pcr(&self, index: u16) -> Result<(bool, Vec<u8>), ErrorCode> {
+        match self.process_request(Request::DescribePCR { index }).await {
+            Response::DescribePCR { lock, data } => Ok((lock, data)),
+            Response::Error(error) => Err(error),
+            _ => Err(ErrorCode::InvalidResponse),
+        }
+    }
+
+    /// Extends the PCR at `index` with `data`, returning its new value
+    pub async fn extend_pcr(&self, index: u16, data: Vec<u8>) -> Result<Vec<u8>, ErrorCode> {
+        match self
+            .process_request(Request::ExtendPCR { index, data })
+            .await
+        {
+            Response::ExtendPCR { data } => Ok(data),
+            Response::Error(error) => Err(error),
+            _ => Err(ErrorCode::InvalidResponse),
+        }
+    }
+
+    /// Requests random bytes from the Nitro Secure Module
+    pub async fn get_random(&self) -> Result<Vec<u8>, ErrorCode> {
+        match self.process_request(Request::GetRandom).await {
+            Response::GetRandom { random } => Ok(random),
+            Response::Error(error) => Err(error),
+            _ => Err(ErrorCode::InvalidResponse),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AsyncDriver, AsyncNsm, SyncDriver};
+    use crate::api::nsm::{ErrorCode, Request, Response};
+    use crate::driver::Driver;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    /// Polls a future that is expected to be ready immediately, avoiding a dependency on an async runtime
+    fn now_or_never<F: Future>(future: F) -> F::Output {
+        const VTABLE: RawWakerVTable = RawWakerVTable::new(
+            |_| RawWaker::new(std::ptr::null(), &VTABLE),
+            |_| {},
+            |_| {},
+            |_| {},
+        );
+        let waker = unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) };
+        match pin!(future).poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("Future should be ready"),
+        }
+    }
+
+    struct Unavailable;
+
+    impl Driver for Unavailable {
+        fn process_request(&self, _: Request) -> Response {
+            Response::Error(ErrorCode::InvalidOperation)
+        }
+    }
+
+    struct Echo;
+
+    impl AsyncDriver for Echo {
+        async fn process_request(&self, request: Request) -> Response {
+            match request {
+                Request::GetRandom => Response::GetRandom {
+                    random: vec![1, 2, 3],
+                },
+                _ => Response::Error(ErrorCode::InvalidOperation),
+            }
+        }
+    }
+
+    #[test]
+    fn sync_driver() {
+        let nsm = SyncDriver(Unavailable);
+        assert!(matches!(
+            now_or_never(nsm.process_request(Request::GetRandom)),
+            Response::Error(ErrorCode::InvalidOperation)
+        ));
+    }
+
+    #[test]
+    fn async_nsm() {
+        let nsm = AsyncNsm::new(Echo);
+        assert_eq!(now_or_never(nsm.get_random()).unwrap(), vec![1, 2, 3]);
+        assert!(matches!(
+            now_or_never(nsm.describe_pcr(0)),
+            Err(ErrorCode::InvalidOperation)
+        ));
+
+        let nsm = AsyncNsm::new(SyncDriver(Unavailable));
+        assert!(matches!(
+            now_or_never(nsm.attestation(None, None, None)),
+            Err(ErrorCode::InvalidOperation)
+        ));
+    }
+
+    #[test]
+    fn async_fn_implementation() {
+        match now_or_never(Echo.process_request(Request::GetRandom)) {
+            Response::GetRandom { random } => assert_eq!(random, vec![1, 2, 3]),
+            _ => panic!("Expected GetRandom response"),
+        }
+    }
+}
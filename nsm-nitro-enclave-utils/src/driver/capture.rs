@@ -0,0 +1,91 @@
+use crate::api::{
+    nsm::{ErrorCode, Request, Response},
+    ByteBuf,
+};
+use crate::driver::Driver;
+
+/// Requests an attestation document from `driver` and returns its COSE bytes, ready to be written to a test fixture.
+/// Fails with the [`ErrorCode`] the Nitro Secure Module responded with, or [`ErrorCode::InvalidResponse`] if it didn't respond with an attestation document.
+///
+/// Supports the "record once, replay locally" workflow: capture an authentic attestation document inside a Nitro Enclave,
+/// then verify the fixture locally against the AWS Nitro Enclaves root certificate.
+/// The certificates in an authentic attestation document are short-lived, so replays must verify at the document's own `timestamp`, i.e. with [`Time::fixed`](crate::time::Time::fixed).
+/// ```rust,no_run
+/// # // `Nitro` requires the `nitro` feature
+/// # #[cfg(feature = "nitro")]
+/// # {
+/// use nsm_nitro_enclave_utils::{api::ByteBuf, driver::{capture_attestation, nitro::Nitro}};
+///
+/// let nsm = Nitro::init();
+/// let document = capture_attestation(&nsm, Some(ByteBuf::from(b"fixture nonce".to_vec()))).unwrap();
+/// std::fs::write("tests/fixtures/attestation_doc.cose", document).unwrap();
+/// # }
+/// ```
+pub fn capture_attestation<D: Driver + ?Sized>(
+    driver: &D,
+    nonce: Option<ByteBuf>,
+) -> Result<Vec<u8>, ErrorCode> {
+    match driver.process_request(Request::Attestation {
+        user_data: None,
+        nonce,
+        public_key: None,
+    }) {
+        Response::Attestation { document } => Ok(document),
+        Response::Error(error) => Err(error),
+        _ => Err(ErrorCode::InvalidResponse),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::capture_attestation;
+    use crate::api::{
+        nsm::{ErrorCode, Request, Response},
+        ByteBuf,
+    };
+    use crate::driver::Driver;
+
+    struct Echo;
+
+    impl Driver for Echo {
+        fn process_request(&self, request: Request) -> Response {
+            match request {
+                Request::Attestation { nonce, .. } => Response::Attestation {
+                    document: nonce.map(ByteBuf::into_vec).unwrap_or_default(),
+                },
+                _ => Response::Error(ErrorCode::InvalidOperation),
+            }
+        }
+    }
+
+    struct Unavailable;
+
+    impl Driver for Unavailable {
+        fn process_request(&self, _: Request) -> Response {
+            Response::Error(ErrorCode::InvalidOperation)
+        }
+    }
+
+    struct Unexpected;
+
+    impl Driver for Unexpected {
+        fn process_request(&self, _: Request) -> Response {
+            Response::LockPCRs
+        }
+    }
+
+    #[test]
+    fn capture() {
+        let document = capture_attestation(&Echo, Some(ByteBuf::from(b"nonce".to_vec()))).unwrap();
+        assert_eq!(document, b"nonce");
+
+        assert!(matches!(
+            capture_attestation(&Unavailable, None),
+            Err(ErrorCode::InvalidOperation)
+        ));
+        assert!(matches!(
+            capture_attestation(&Unexpected, None),
+            Err(ErrorCode::InvalidResponse)
+        ));
+    }
+}
@@ -1,99 +1,384 @@
 use crate::api::{
-    nsm::{AttestationDoc, ErrorCode, Request, Response},
-    ByteBuf, SecretKey,
+    nsm::{AttestationDoc, Digest, ErrorCode, Request, Response},
+    ByteBuf, SecretKey, MAX_DOCUMENT_SIZE,
 };
-use crate::driver::dev::sign::AttestationDocSignerExt;
+use crate::driver::dev::sign::{AttestationDocSignerExt, CoseSigningKey};
 use crate::driver::Driver;
-use crate::pcr::Pcrs;
+use crate::pcr::{PcrIndex, Pcrs, PCR_INDEXES};
 use crate::time::Time;
-use p384::ecdsa::SigningKey;
+use coset::{iana::Algorithm, CborSerializable, CoseSign1};
+use p384::pkcs8::DecodePrivateKey;
+use std::collections::BTreeSet;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex, MutexGuard, PoisonError,
+};
+use x509_cert::{
+    der::{Decode, DecodePem, Encode},
+    Certificate,
+};
+
+/// The `module_id` of every attestation document produced by [`DevNitro`], unless configured with [`DevNitroBuilder::module_id`]
+pub const DEFAULT_MODULE_ID: &str = "unsecure-development-attestation-document";
+
+/// Returned when a [`DevNitro`] can't be built, see [`DevNitroBuilder::try_build`], or a document can't be re-signed, see [`resign`]
+pub type DevNitroError = crate::Error<ErrorKind>;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub enum ErrorKind {
+    /// The signing key couldn't be parsed
+    SigningKey,
+    /// The end certificate couldn't be parsed, or its public key isn't on the signing key's curve
+    EndCertificate,
+    /// The end certificate's public key doesn't belong to the signing key
+    PublicKeyMismatch,
+    /// The COSE document couldn't be parsed or serialized
+    Cose,
+}
 
 /// [`DevNitro`] mimics requests to the Nitro Secure Module, allowing you to build features for AWS Nitro Enclaves, without AWS Nitro Enclaves.
 pub struct DevNitro {
     ca_bundle: Vec<ByteBuf>,
-    signing_key: SigningKey,
+    signer: Signer,
     end_cert: ByteBuf,
-    pcrs: Pcrs,
+    module_id: String,
+    digest: Option<Digest>,
+    key_id: Option<Vec<u8>>,
+    public_key: Option<ByteBuf>,
+    pcrs: Mutex<PcrState>,
     get_timestamp: Time,
+    get_random: Option<Box<dyn Fn() -> Vec<u8> + Send + Sync>>,
+    failure: Option<Failure>,
+    malformations: Malformations,
+    max_document_size: usize,
+    attestation_count: AtomicUsize,
+}
+
+/// Produces the raw signature over the COSE `Sig_structure` bytes
+type SignFn = Box<dyn Fn(&[u8]) -> Vec<u8> + Send + Sync>;
+
+/// Signs the attestation documents, either in-process or via [`DevNitro::builder_with_signer`]
+enum Signer {
+    Key(Box<CoseSigningKey>),
+    External(Algorithm, SignFn),
+}
+
+/// Configured via [`DevNitroBuilder::fail_every_nth`]
+struct Failure {
+    every: usize,
+    error: ErrorCode,
+}
+
+/// Configured via the `DevNitroBuilder::malformed_*` methods, each breaks the attestation documents in a way verification must reject
+#[derive(Default, Clone, Copy)]
+struct Malformations {
+    missing_intermediates: bool,
+    missing_certificate: bool,
+    unchained_certificate: bool,
+}
+
+/// The PCRs are shared between requests, as `ExtendPCR` requests modify them and `LockPCR(s)` requests prevent further modifications
+struct PcrState {
+    pcrs: Pcrs,
+    locked: BTreeSet<PcrIndex>,
 }
 
 impl Driver for DevNitro {
     fn process_request(&self, request: Request) -> Response {
         match request {
             Request::DescribePCR { index } => self.describe_pcr(index),
+            Request::ExtendPCR { index, data } => self.extend_pcr(index, &data),
+            Request::LockPCR { index } => self.lock_pcr(index),
+            Request::LockPCRs { range } => self.lock_pcrs(range),
             Request::Attestation {
                 user_data,
                 nonce,
                 public_key,
             } => self.attestation(user_data, nonce, public_key),
+            Request::GetRandom => self.get_random(),
             _ => Response::Error(ErrorCode::InvalidOperation),
         }
     }
 }
 
 impl DevNitro {
-    /// `signing_key`: used to sign the attestation document
+    /// `signing_key`: used to sign the attestation document, with the algorithm matching its curve, see [`CoseSigningKey`].
+    /// Authentic attestation documents are signed with a P-384 [`SecretKey`], other curves produce documents verifiers reject.
     /// `end_cert` a der encoded x509 certificate. Should contain `signing_key`'s public key.
-    pub fn builder(signing_key: SecretKey, end_cert: ByteBuf) -> DevNitroBuilder {
+    pub fn builder(signing_key: impl Into<CoseSigningKey>, end_cert: ByteBuf) -> DevNitroBuilder {
+        Self::builder_with(Signer::Key(Box::new(signing_key.into())), end_cert)
+    }
+
+    /// Behaves like [`DevNitro::builder`], signing the attestation document with an external signer, i.e. a key held by an HSM or KMS.
+    /// `sign_fn` receives the COSE `Sig_structure` bytes and must return the raw (`r || s`) signature, as produced by `alg`.
+    /// Authentic attestation documents are signed with [`Algorithm::ES384`].
+    /// `end_cert` a der encoded x509 certificate. Should contain the signer's public key.
+    pub fn builder_with_signer(
+        alg: Algorithm,
+        sign_fn: SignFn,
+        end_cert: ByteBuf,
+    ) -> DevNitroBuilder {
+        Self::builder_with(Signer::External(alg, sign_fn), end_cert)
+    }
+
+    /// The names of the [`Request`] variants [`Driver::process_request`] handles, every other variant fails with `ErrorCode::InvalidOperation`.
+    /// The list doesn't depend on the enabled features. `GetRandom` requires a source, see [`DevNitroBuilder::get_random`].
+    pub fn supported_requests() -> &'static [&'static str] {
+        &[
+            "DescribePCR",
+            "ExtendPCR",
+            "LockPCR",
+            "LockPCRs",
+            "Attestation",
+            "GetRandom",
+        ]
+    }
+
+    /// Produces a signed COSE attestation document, like an `Attestation` request to [`Driver::process_request`] would, without matching on the [`Response`].
+    /// Fails with the [`ErrorCode`] the `Attestation` request would respond with.
+    pub fn attest(
+        &self,
+        nonce: Option<ByteBuf>,
+        user_data: Option<ByteBuf>,
+        public_key: Option<ByteBuf>,
+    ) -> Result<Vec<u8>, ErrorCode> {
+        self.attest_at(self.get_timestamp.time(), nonce, user_data, public_key)
+    }
+
+    /// Behaves like [`DevNitro::attest`], stamping the attestation document with `timestamp`, expressed as milliseconds since Unix Epoch, instead of the builder's [`Time`].
+    /// Useful for producing a sequence of documents with controlled timestamps, i.e. to test a verifier's freshness window.
+    pub fn attest_at(
+        &self,
+        timestamp: u64,
+        nonce: Option<ByteBuf>,
+        user_data: Option<ByteBuf>,
+        public_key: Option<ByteBuf>,
+    ) -> Result<Vec<u8>, ErrorCode> {
+        let count = self.attestation_count.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(failure) = &self.failure {
+            if failure.every != 0 && count % failure.every == 0 {
+                return Err(copy_error_code(&failure.error));
+            }
+        }
+
+        let mut certificate = self.end_cert.clone();
+        if self.malformations.missing_certificate {
+            certificate.clear();
+        } else if self.malformations.unchained_certificate {
+            // A DER encoded certificate ends with its signature, flipping the last byte keeps the certificate decodable
+            if let Some(byte) = certificate.last_mut() {
+                *byte ^= 0xff;
+            }
+        }
+        let cabundle = match self.malformations.missing_intermediates {
+            true => Vec::new(),
+            false => self.ca_bundle.clone(),
+        };
+
+        let pcrs = self.pcrs().pcrs.clone();
+        let doc = AttestationDoc {
+            module_id: self.module_id.clone(),
+            digest: self.digest.unwrap_or(Digest::SHA384),
+            timestamp,
+            pcrs: pcrs.into(),
+            certificate,
+            cabundle,
+            user_data,
+            nonce,
+            public_key: public_key.or_else(|| self.public_key.clone()),
+        };
+
+        let document = match (&self.signer, &self.key_id) {
+            (Signer::Key(signing_key), None) => doc.sign(signing_key.as_ref().clone()),
+            (Signer::Key(signing_key), Some(key_id)) => {
+                doc.sign_with_kid(signing_key.as_ref().clone(), key_id)
+            }
+            (Signer::External(alg, sign_fn), key_id) => {
+                doc.sign_with(*alg, key_id.as_deref().unwrap_or_default(), sign_fn)
+            }
+        };
+
+        let document = document.map_err(|_| ErrorCode::InternalError)?;
+        if document.len() > self.max_document_size {
+            return Err(ErrorCode::InputTooLarge);
+        }
+
+        Ok(document)
+    }
+
+    fn builder_with(signer: Signer, end_cert: ByteBuf) -> DevNitroBuilder {
         DevNitroBuilder {
-            signing_key: signing_key.into(),
+            signer,
             end_cert,
             ca_bundle: None,
+            module_id: DEFAULT_MODULE_ID.to_string(),
+            digest: None,
+            key_id: None,
+            public_key: None,
             pcrs: Pcrs::default(),
             get_timestamp: Time::system_time(),
+            get_random: default_get_random(),
+            failure: None,
+            malformations: Malformations::default(),
+            max_document_size: MAX_DOCUMENT_SIZE,
         }
     }
 
+    fn pcrs(&self) -> MutexGuard<'_, PcrState> {
+        self.pcrs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
     fn describe_pcr(&self, index: u16) -> Response {
         let index = usize::from(index);
         match index.try_into() {
             Ok(index) => {
-                let pcr = self.pcrs.get(index);
+                let state = self.pcrs();
                 Response::DescribePCR {
-                    lock: true,
-                    data: pcr.to_vec(),
+                    lock: state.locked.contains(&index),
+                    data: state.pcrs.get(index).to_vec(),
                 }
             }
             Err(_) => Response::Error(ErrorCode::InvalidIndex),
         }
     }
 
+    fn lock_pcr(&self, index: u16) -> Response {
+        let Ok(index) = PcrIndex::try_from(usize::from(index)) else {
+            return Response::Error(ErrorCode::InvalidIndex);
+        };
+
+        self.pcrs().locked.insert(index);
+        Response::LockPCR
+    }
+
+    /// Locks PCRs `[0, range)`
+    fn lock_pcrs(&self, range: u16) -> Response {
+        let indexes = (0..usize::from(range))
+            .map(PcrIndex::try_from)
+            .collect::<Result<Vec<PcrIndex>, _>>();
+        let Ok(indexes) = indexes else {
+            return Response::Error(ErrorCode::InvalidIndex);
+        };
+
+        self.pcrs().locked.extend(indexes);
+        Response::LockPCRs
+    }
+
+    fn extend_pcr(&self, index: u16, data: &[u8]) -> Response {
+        let Ok(index) = PcrIndex::try_from(usize::from(index)) else {
+            return Response::Error(ErrorCode::InvalidIndex);
+        };
+
+        let mut state = self.pcrs();
+        if state.locked.contains(&index) {
+            return Response::Error(ErrorCode::ReadOnlyIndex);
+        }
+
+        state.pcrs.extend(index, data);
+        Response::ExtendPCR {
+            data: state.pcrs.get(index).to_vec(),
+        }
+    }
+
+    fn get_random(&self) -> Response {
+        match &self.get_random {
+            Some(get_random) => Response::GetRandom {
+                random: get_random(),
+            },
+            None => Response::Error(ErrorCode::InvalidOperation),
+        }
+    }
+
     fn attestation(
         &self,
         user_data: Option<ByteBuf>,
         nonce: Option<ByteBuf>,
         public_key: Option<ByteBuf>,
     ) -> Response {
-        let doc = AttestationDoc {
-            module_id: "unsecure-development-attestation-document".to_string(),
-            digest: aws_nitro_enclaves_nsm_api::api::Digest::SHA384,
-            timestamp: self.get_timestamp.time(),
-            pcrs: self.pcrs.clone().into(),
-            certificate: self.end_cert.clone(),
-            cabundle: self.ca_bundle.clone(),
-            user_data,
-            nonce,
-            public_key,
-        };
-
-        if let Ok(document) = doc.sign(self.signing_key.clone()) {
-            return Response::Attestation { document };
+        match self.attest(nonce, user_data, public_key) {
+            Ok(document) => Response::Attestation { document },
+            Err(error) => Response::Error(error),
         }
+    }
+}
 
-        Response::Error(ErrorCode::InternalError)
+/// [`ErrorCode`] doesn't implement `Clone`
+fn copy_error_code(error: &ErrorCode) -> ErrorCode {
+    match error {
+        ErrorCode::Success => ErrorCode::Success,
+        ErrorCode::InvalidArgument => ErrorCode::InvalidArgument,
+        ErrorCode::InvalidIndex => ErrorCode::InvalidIndex,
+        ErrorCode::InvalidResponse => ErrorCode::InvalidResponse,
+        ErrorCode::ReadOnlyIndex => ErrorCode::ReadOnlyIndex,
+        ErrorCode::InvalidOperation => ErrorCode::InvalidOperation,
+        ErrorCode::BufferTooSmall => ErrorCode::BufferTooSmall,
+        ErrorCode::InputTooLarge => ErrorCode::InputTooLarge,
+        ErrorCode::InternalError => ErrorCode::InternalError,
     }
 }
 
+/// The amount of bytes returned by an authentic Nitro Secure Module for each `GetRandom` request
+#[cfg(feature = "rand")]
+const RANDOM_LENGTH: usize = 256;
+
+#[cfg(feature = "rand")]
+fn default_get_random() -> Option<Box<dyn Fn() -> Vec<u8> + Send + Sync>> {
+    use rand::RngCore;
+    Some(Box::new(|| {
+        let mut random = vec![0; RANDOM_LENGTH];
+        rand::thread_rng().fill_bytes(&mut random);
+        random
+    }))
+}
+
+#[cfg(not(feature = "rand"))]
+fn default_get_random() -> Option<Box<dyn Fn() -> Vec<u8> + Send + Sync>> {
+    None
+}
+
 /// A builder for [`DevNitro`]
 pub struct DevNitroBuilder {
-    signing_key: SigningKey,
+    signer: Signer,
     end_cert: ByteBuf,
     ca_bundle: Option<Vec<ByteBuf>>,
+    module_id: String,
+    digest: Option<Digest>,
+    key_id: Option<Vec<u8>>,
+    public_key: Option<ByteBuf>,
     pcrs: Pcrs,
     get_timestamp: Time,
+    get_random: Option<Box<dyn Fn() -> Vec<u8> + Send + Sync>>,
+    failure: Option<Failure>,
+    malformations: Malformations,
+    max_document_size: usize,
 }
 
 impl DevNitroBuilder {
+    /// Behaves like [`DevNitro::builder`], parsing a DER encoded PKCS#8 `signing_key` and a DER encoded x509 `end_cert`.
+    pub fn try_from_der(signing_key: &[u8], end_cert: &[u8]) -> Result<Self, DevNitroError> {
+        let signing_key = SecretKey::from_pkcs8_der(signing_key)
+            .map_err(|err| DevNitroError::new(ErrorKind::SigningKey, err))?;
+        // Ensures `end_cert` is a certificate before it's included in attestation documents
+        Certificate::from_der(end_cert)
+            .map_err(|err| DevNitroError::new(ErrorKind::EndCertificate, err))?;
+
+        Ok(DevNitro::builder(
+            signing_key,
+            ByteBuf::from(end_cert.to_vec()),
+        ))
+    }
+
+    /// Behaves like [`DevNitro::builder`], parsing a PEM encoded PKCS#8 `signing_key` and a PEM encoded x509 `end_cert`.
+    pub fn try_from_pem(signing_key: &str, end_cert: &str) -> Result<Self, DevNitroError> {
+        let signing_key = SecretKey::from_pkcs8_pem(signing_key)
+            .map_err(|err| DevNitroError::new(ErrorKind::SigningKey, err))?;
+        let end_cert = Certificate::from_pem(end_cert)
+            .and_then(|cert| cert.to_der())
+            .map_err(|err| DevNitroError::new(ErrorKind::EndCertificate, err))?;
+
+        Ok(DevNitro::builder(signing_key, ByteBuf::from(end_cert)))
+    }
+
     /// Set the attestation document's ca_bundle.
     /// `ca_bundle` should be a list of der encoded intermediate certificates.
     pub fn ca_bundle(self, ca_bundle: Vec<ByteBuf>) -> Self {
@@ -103,19 +388,797 @@ impl DevNitroBuilder {
         }
     }
 
-    /// Set attestation document's PCRs
+    /// Set the attestation document's `module_id`. Defaults to [`DEFAULT_MODULE_ID`].
+    pub fn module_id(self, module_id: String) -> Self {
+        Self { module_id, ..self }
+    }
+
+    /// Set the attestation document's `digest`. Defaults to [`Digest::SHA384`], the only `digest` the verifier accepts, see [`VerifiedAttestationDoc::digest`](crate::verify::VerifiedAttestationDoc::digest).
+    /// Overriding it allows simulating a module whose `digest` the verifier rejects.
+    pub fn digest(self, digest: Digest) -> Self {
+        Self {
+            digest: Some(digest),
+            ..self
+        }
+    }
+
+    /// Set the COSE `kid` protected header of the attestation documents, identifying the key that signed them. Unset by default, matching authentic attestation documents.
+    pub fn key_id(self, key_id: Vec<u8>) -> Self {
+        Self {
+            key_id: Some(key_id),
+            ..self
+        }
+    }
+
+    /// Set the `public_key` included in every attestation document, mirroring an enclave that generated its key pair at boot.
+    /// A `public_key` provided by the `Attestation` request takes precedence. Defaults to none.
+    pub fn public_key(self, public_key: ByteBuf) -> Self {
+        Self {
+            public_key: Some(public_key),
+            ..self
+        }
+    }
+
+    /// Set attestation document's PCRs.
+    /// These are the initial values, `ExtendPCR` requests modify them while the [`DevNitro`] is in use.
     pub fn pcrs(self, pcrs: Pcrs) -> Self {
         Self { pcrs, ..self }
     }
 
+    /// Set the [`Time`] used for the attestation document's `timestamp`, which is expressed as milliseconds since Unix Epoch like every [`Time`]. Defaults to [`Time::system_time`].
+    pub fn get_timestamp(self, get_timestamp: Time) -> Self {
+        Self {
+            get_timestamp,
+            ..self
+        }
+    }
+
+    /// Set the source of the bytes returned by `GetRandom` requests, i.e. a deterministic source for reproducible tests.
+    /// With the `rand` feature enabled this defaults to 256 bytes from a CSPRNG, mirroring the Nitro Secure Module.
+    /// Without it, and without a source, `GetRandom` requests fail with `ErrorCode::InvalidOperation`.
+    pub fn get_random(self, get_random: Box<dyn Fn() -> Vec<u8> + Send + Sync>) -> Self {
+        Self {
+            get_random: Some(get_random),
+            ..self
+        }
+    }
+
+    /// Fail every `Attestation` request with `error`, allowing the error handling of code that requests attestation documents to be tested.
+    pub fn fail_with(self, error: ErrorCode) -> Self {
+        self.fail_every_nth(1, error)
+    }
+
+    /// Fail every `n`th `Attestation` request with `error`, starting with the `n`th request. Other requests are unaffected.
+    /// An `n` of zero never fails.
+    pub fn fail_every_nth(self, n: usize, error: ErrorCode) -> Self {
+        Self {
+            failure: Some(Failure { every: n, error }),
+            ..self
+        }
+    }
+
+    /// Produce attestation documents with an empty `cabundle`, whose certificate can't be chained to the root certificate.
+    /// Verification fails with `ErrorKind::UnknownIssuer`, allowing the negative paths of a verifier to be tested.
+    pub fn malformed_missing_intermediates(self) -> Self {
+        Self {
+            malformations: Malformations {
+                missing_intermediates: true,
+                ..self.malformations
+            },
+            ..self
+        }
+    }
+
+    /// Produce attestation documents with an empty `certificate`.
+    /// Verification fails with `ErrorKind::EndCertificate`, allowing the negative paths of a verifier to be tested.
+    pub fn malformed_missing_certificate(self) -> Self {
+        Self {
+            malformations: Malformations {
+                missing_certificate: true,
+                ..self.malformations
+            },
+            ..self
+        }
+    }
+
+    /// Produce attestation documents whose `certificate` has a corrupted signature, so it doesn't chain to the root certificate.
+    /// Verification fails with `ErrorKind::BadSignature`, allowing the negative paths of a verifier to be tested.
+    pub fn malformed_unchained_certificate(self) -> Self {
+        Self {
+            malformations: Malformations {
+                unchained_certificate: true,
+                ..self.malformations
+            },
+            ..self
+        }
+    }
+
+    /// Set the maximum size, in bytes, of the COSE encoded attestation documents. Defaults to [`MAX_DOCUMENT_SIZE`].
+    /// `Attestation` requests whose `user_data`, `nonce` or `public_key` push the document over the limit fail with `ErrorCode::InputTooLarge`,
+    /// catching documents that this mock would produce, but the Nitro Secure Module wouldn't.
+    pub fn max_document_size(self, max_document_size: usize) -> Self {
+        Self {
+            max_document_size,
+            ..self
+        }
+    }
+
+    /// Behaves like [`DevNitroBuilder::build`], first checking that the end certificate contains the signing key's public key.
+    /// Without this check, a mismatched signing key and end certificate produce attestation documents that fail verification.
+    /// An external signer, provided to [`DevNitro::builder_with_signer`], can't be checked.
+    pub fn try_build(self) -> Result<DevNitro, DevNitroError> {
+        if let Signer::Key(signing_key) = &self.signer {
+            let end_cert = Certificate::from_der(&self.end_cert)
+                .map_err(|err| DevNitroError::new(ErrorKind::EndCertificate, err))?;
+            let public_key = end_cert
+                .tbs_certificate
+                .subject_public_key_info
+                .subject_public_key
+                .as_bytes()
+                .ok_or(DevNitroError::new(
+                    ErrorKind::EndCertificate,
+                    crate::ErrorContext("End certificate's public key has unused bits"),
+                ))?;
+            let matches = signing_key
+                .matches_public_key(public_key)
+                .map_err(|err| DevNitroError::new(ErrorKind::EndCertificate, err))?;
+
+            if !matches {
+                return Err(DevNitroError::new(
+                    ErrorKind::PublicKeyMismatch,
+                    crate::ErrorContext(
+                        "End certificate doesn't contain the signing key's public key",
+                    ),
+                ));
+            }
+        }
+
+        Ok(self.build())
+    }
+
     /// Builds a new [`DevNitro`] to processes the requests
     pub fn build(self) -> DevNitro {
         DevNitro {
-            signing_key: self.signing_key,
+            signer: self.signer,
             end_cert: self.end_cert,
             ca_bundle: self.ca_bundle.unwrap_or_default(),
-            pcrs: self.pcrs,
+            module_id: self.module_id,
+            digest: self.digest,
+            key_id: self.key_id,
+            public_key: self.public_key,
+            pcrs: Mutex::new(PcrState {
+                pcrs: self.pcrs,
+                // PCRs 0 through 15 are locked by the Nitro Hypervisor at boot, 16 through 31 are reserved for custom measurements
+                locked: PCR_INDEXES.into_iter().collect(),
+            }),
             get_timestamp: self.get_timestamp,
+            get_random: self.get_random,
+            failure: self.failure,
+            malformations: self.malformations,
+            max_document_size: self.max_document_size,
+            attestation_count: AtomicUsize::new(0),
+        }
+    }
+}
+
+/// Replaces the signature of a COSE signed attestation document with one produced by `signing_key`.
+/// The payload and headers, including the protected header's `alg`, are preserved byte for byte, even if `signing_key` signs with another algorithm.
+///
+/// Useful for building documents that only differ from a valid document by their signature, i.e. to check that a verifier rejects documents signed by a key other than the end certificate's.
+pub fn resign(
+    cose: &[u8],
+    signing_key: impl Into<CoseSigningKey>,
+) -> Result<Vec<u8>, DevNitroError> {
+    let mut cose_sign1 =
+        CoseSign1::from_slice(cose).map_err(|err| DevNitroError::new(ErrorKind::Cose, err))?;
+
+    cose_sign1.signature = signing_key.into().sign(&cose_sign1.tbs_data(b""));
+
+    cose_sign1
+        .to_vec()
+        .map_err(|err| DevNitroError::new(ErrorKind::Cose, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DevNitro, DevNitroBuilder};
+    use crate::api::{
+        nsm::{AttestationDoc, Digest, ErrorCode, Request, Response},
+        ByteBuf, SecretKey,
+    };
+    use crate::driver::Driver;
+    use crate::pcr::{PcrDigest, PcrIndex, Pcrs};
+    use coset::CborSerializable;
+    use nsm_nitro_enclave_utils_keygen::DerEncodeExt;
+    use std::time::Duration;
+
+    /// A [`DevNitroBuilder`] signing with a freshly generated end certificate
+    fn builder() -> DevNitroBuilder {
+        let cert_chain =
+            nsm_nitro_enclave_utils_keygen::NsmCertChain::generate(Duration::from_secs(60));
+        let signing_key = cert_chain.end_signer.signing_key.as_p384().unwrap();
+        DevNitro::builder(
+            SecretKey::from(signing_key.as_nonzero_scalar()),
+            ByteBuf::from(cert_chain.end_signer.cert.to_der().unwrap()),
+        )
+    }
+
+    fn dev_nitro(pcrs: Pcrs) -> DevNitro {
+        builder().pcrs(pcrs).build()
+    }
+
+    #[test]
+    fn describe_pcr() {
+        let mut pcrs = Pcrs::zeros();
+        pcrs.set(PcrIndex::Five, [5; 48].into()).unwrap();
+        pcrs.set(PcrIndex::Sixteen, [16; 48].into()).unwrap();
+        let nsm = dev_nitro(pcrs);
+
+        for (index, expected, expected_lock) in [(5, [5; 48], true), (16, [16; 48], false)] {
+            match nsm.process_request(Request::DescribePCR { index }) {
+                Response::DescribePCR { lock, data } => {
+                    assert_eq!(data, expected);
+                    assert_eq!(lock, expected_lock);
+                }
+                _ => panic!("Expected DescribePCR response"),
+            }
         }
+
+        assert!(matches!(
+            nsm.process_request(Request::DescribePCR { index: 32 }),
+            Response::Error(ErrorCode::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    fn supported_requests() {
+        let nsm = builder().get_random(Box::new(|| vec![4; 4])).build();
+
+        let requests = [
+            ("DescribePCR", Request::DescribePCR { index: 0 }),
+            (
+                "ExtendPCR",
+                Request::ExtendPCR {
+                    index: 16,
+                    data: b"data".to_vec(),
+                },
+            ),
+            ("LockPCR", Request::LockPCR { index: 17 }),
+            ("LockPCRs", Request::LockPCRs { range: 0 }),
+            ("DescribeNSM", Request::DescribeNSM),
+            (
+                "Attestation",
+                Request::Attestation {
+                    user_data: None,
+                    nonce: None,
+                    public_key: None,
+                },
+            ),
+            ("GetRandom", Request::GetRandom),
+        ];
+        for (name, request) in requests {
+            let unsupported = matches!(
+                nsm.process_request(request),
+                Response::Error(ErrorCode::InvalidOperation)
+            );
+            assert_eq!(
+                DevNitro::supported_requests().contains(&name),
+                !unsupported,
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn attestation_digest_is_sha384() {
+        for (pcrs, pcr_len) in [
+            (Pcrs::zeros(), PcrDigest::Sha384.pcr_length()),
+            (
+                Pcrs::zeros_with_digest(PcrDigest::Sha256),
+                PcrDigest::Sha256.pcr_length(),
+            ),
+        ] {
+            let nsm = dev_nitro(pcrs);
+            let document = match nsm.process_request(Request::Attestation {
+                user_data: None,
+                nonce: None,
+                public_key: None,
+            }) {
+                Response::Attestation { document } => document,
+                _ => panic!("Expected Attestation response"),
+            };
+
+            let cose = coset::CoseSign1::from_slice(&document).unwrap();
+            let doc = AttestationDoc::from_binary(&cose.payload.unwrap()).unwrap();
+            assert_eq!(doc.digest, Digest::SHA384);
+            assert!(doc.pcrs.values().all(|pcr| pcr.len() == pcr_len));
+        }
+    }
+
+    #[test]
+    fn attest() {
+        let nsm = dev_nitro(Pcrs::zeros());
+        let document = nsm
+            .attest(Some(ByteBuf::from(b"nonce".to_vec())), None, None)
+            .unwrap();
+        let cose = coset::CoseSign1::from_slice(&document).unwrap();
+        let doc = AttestationDoc::from_binary(&cose.payload.unwrap()).unwrap();
+        assert_eq!(doc.nonce.unwrap(), b"nonce");
+    }
+
+    #[test]
+    fn attest_at() {
+        let nsm = dev_nitro(Pcrs::zeros());
+        for timestamp in [1_000, 2_000, 3_000] {
+            let document = nsm.attest_at(timestamp, None, None, None).unwrap();
+            let cose = coset::CoseSign1::from_slice(&document).unwrap();
+            let doc = AttestationDoc::from_binary(&cose.payload.unwrap()).unwrap();
+            assert_eq!(doc.timestamp, timestamp);
+        }
+    }
+
+    #[test]
+    fn public_key() {
+        let public_key = |nsm: &DevNitro, public_key: Option<&[u8]>| {
+            let document = nsm
+                .attest(
+                    None,
+                    None,
+                    public_key.map(|key| ByteBuf::from(key.to_vec())),
+                )
+                .unwrap();
+            let cose = coset::CoseSign1::from_slice(&document).unwrap();
+            AttestationDoc::from_binary(&cose.payload.unwrap())
+                .unwrap()
+                .public_key
+        };
+
+        let nsm = builder()
+            .public_key(ByteBuf::from(b"enclave".to_vec()))
+            .build();
+
+        assert_eq!(public_key(&nsm, None).unwrap(), b"enclave");
+        assert_eq!(public_key(&nsm, Some(b"request")).unwrap(), b"request");
+        assert!(public_key(&dev_nitro(Pcrs::zeros()), None).is_none());
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn malformed() {
+        use crate::verify::{AttestationVerifier, ErrorKind};
+
+        let cert_chain =
+            nsm_nitro_enclave_utils_keygen::NsmCertChain::generate(Duration::from_secs(60));
+        let builder = || cert_chain.dev_nitro_builder().unwrap();
+        let verifier = AttestationVerifier::new(cert_chain.root_signer.cert.to_der().unwrap());
+
+        let document = builder().build().attest(None, None, None).unwrap();
+        verifier.verify(&document).unwrap();
+
+        for (nsm, expected) in [
+            (
+                builder().malformed_missing_intermediates().build(),
+                ErrorKind::UnknownIssuer,
+            ),
+            (
+                builder().malformed_missing_certificate().build(),
+                ErrorKind::EndCertificate,
+            ),
+            (
+                builder().malformed_unchained_certificate().build(),
+                ErrorKind::BadSignature,
+            ),
+        ] {
+            let document = nsm.attest(None, None, None).unwrap();
+            let err = verifier.verify(&document).unwrap_err();
+            assert_eq!(err.kind(), &expected);
+        }
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn resign() {
+        use crate::verify::{AttestationVerifier, ErrorKind};
+        use coset::CoseSign1;
+
+        let cert_chain =
+            nsm_nitro_enclave_utils_keygen::NsmCertChain::generate(Duration::from_secs(60));
+        let signing_key = cert_chain.end_signer.signing_key.as_p384().unwrap();
+        let document = cert_chain
+            .dev_nitro_builder()
+            .unwrap()
+            .build()
+            .attest(None, None, None)
+            .unwrap();
+        let verifier = AttestationVerifier::new(cert_chain.root_signer.cert.to_der().unwrap());
+
+        // Re-signing with the end certificate's key produces a document that still verifies
+        let resigned =
+            super::resign(&document, SecretKey::from(signing_key.as_nonzero_scalar())).unwrap();
+        verifier.verify(&resigned).unwrap();
+
+        let other_key = SecretKey::random(&mut rand::thread_rng());
+        let resigned = super::resign(&document, other_key).unwrap();
+        let original = CoseSign1::from_slice(&document).unwrap();
+        let cross_signed = CoseSign1::from_slice(&resigned).unwrap();
+        assert_eq!(original.payload, cross_signed.payload);
+        assert_eq!(
+            original.protected.original_data,
+            cross_signed.protected.original_data
+        );
+        assert_ne!(original.signature, cross_signed.signature);
+
+        let err = verifier.verify(&resigned).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::Verification);
+
+        let err =
+            super::resign(b"not cose", SecretKey::random(&mut rand::thread_rng())).unwrap_err();
+        assert_eq!(err.kind(), &super::ErrorKind::Cose);
+    }
+
+    #[test]
+    fn max_document_size() {
+        let nsm = dev_nitro(Pcrs::zeros());
+        let document = nsm.attest(None, None, None).unwrap();
+        assert!(document.len() <= super::MAX_DOCUMENT_SIZE);
+
+        let user_data = Some(ByteBuf::from(vec![0; super::MAX_DOCUMENT_SIZE]));
+        assert!(matches!(
+            nsm.attest(None, user_data, None),
+            Err(ErrorCode::InputTooLarge)
+        ));
+    }
+
+    #[test]
+    fn get_random() {
+        let nsm = builder().get_random(Box::new(|| vec![7; 4])).build();
+
+        match nsm.process_request(Request::GetRandom) {
+            Response::GetRandom { random } => assert_eq!(random, vec![7; 4]),
+            _ => panic!("Expected GetRandom response"),
+        }
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn get_random_defaults_to_csprng() {
+        let nsm = dev_nitro(Pcrs::zeros());
+
+        let get_random = || match nsm.process_request(Request::GetRandom) {
+            Response::GetRandom { random } => random,
+            _ => panic!("Expected GetRandom response"),
+        };
+        let a = get_random();
+        let b = get_random();
+        assert_eq!(a.len(), 256);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn extend_pcr() {
+        let nsm = dev_nitro(Pcrs::zeros());
+        let mut expected = Pcrs::zeros();
+        expected.extend(PcrIndex::Sixteen, b"boot");
+
+        let extended = match nsm.process_request(Request::ExtendPCR {
+            index: 16,
+            data: b"boot".to_vec(),
+        }) {
+            Response::ExtendPCR { data } => data,
+            _ => panic!("Expected ExtendPCR response"),
+        };
+        assert_eq!(extended, expected.get(PcrIndex::Sixteen).to_vec());
+
+        match nsm.process_request(Request::DescribePCR { index: 16 }) {
+            Response::DescribePCR { data, .. } => assert_eq!(data, extended),
+            _ => panic!("Expected DescribePCR response"),
+        }
+
+        let document = match nsm.process_request(Request::Attestation {
+            user_data: None,
+            nonce: None,
+            public_key: None,
+        }) {
+            Response::Attestation { document } => document,
+            _ => panic!("Expected Attestation response"),
+        };
+        let cose = coset::CoseSign1::from_slice(&document).unwrap();
+        let doc = AttestationDoc::from_binary(&cose.payload.unwrap()).unwrap();
+        assert_eq!(doc.pcrs.get(&16).unwrap().to_vec(), extended);
+
+        assert!(matches!(
+            nsm.process_request(Request::ExtendPCR {
+                index: 32,
+                data: b"boot".to_vec(),
+            }),
+            Response::Error(ErrorCode::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    fn lock_pcr() {
+        let nsm = dev_nitro(Pcrs::zeros());
+        let extend = |index| {
+            nsm.process_request(Request::ExtendPCR {
+                index,
+                data: b"boot".to_vec(),
+            })
+        };
+        let locked = |index| match nsm.process_request(Request::DescribePCR { index }) {
+            Response::DescribePCR { lock, .. } => lock,
+            _ => panic!("Expected DescribePCR response"),
+        };
+
+        assert!(matches!(
+            extend(0),
+            Response::Error(ErrorCode::ReadOnlyIndex)
+        ));
+
+        assert!(!locked(16));
+        assert!(matches!(extend(16), Response::ExtendPCR { .. }));
+        assert!(matches!(
+            nsm.process_request(Request::LockPCR { index: 16 }),
+            Response::LockPCR
+        ));
+        assert!(locked(16));
+        assert!(matches!(
+            extend(16),
+            Response::Error(ErrorCode::ReadOnlyIndex)
+        ));
+
+        assert!(matches!(
+            nsm.process_request(Request::LockPCRs { range: 20 }),
+            Response::LockPCRs
+        ));
+        assert!(locked(19));
+        assert!(!locked(20));
+        assert!(matches!(
+            extend(19),
+            Response::Error(ErrorCode::ReadOnlyIndex)
+        ));
+        assert!(matches!(extend(20), Response::ExtendPCR { .. }));
+
+        assert!(matches!(
+            nsm.process_request(Request::LockPCR { index: 32 }),
+            Response::Error(ErrorCode::InvalidIndex)
+        ));
+        assert!(matches!(
+            nsm.process_request(Request::LockPCRs { range: 33 }),
+            Response::Error(ErrorCode::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    fn module_id_and_digest() {
+        let attest = |nsm: DevNitro| match nsm.process_request(Request::Attestation {
+            user_data: None,
+            nonce: None,
+            public_key: None,
+        }) {
+            Response::Attestation { document } => {
+                let cose = coset::CoseSign1::from_slice(&document).unwrap();
+                AttestationDoc::from_binary(&cose.payload.unwrap()).unwrap()
+            }
+            _ => panic!("Expected Attestation response"),
+        };
+
+        let doc = attest(dev_nitro(Pcrs::zeros()));
+        assert_eq!(doc.module_id, super::DEFAULT_MODULE_ID);
+        assert_eq!(doc.digest, Digest::SHA384);
+
+        let nsm = builder()
+            .module_id("i-1234567890abcdef0-enc0123456789abcdef".to_string())
+            .digest(Digest::SHA256)
+            .build();
+        let doc = attest(nsm);
+        assert_eq!(doc.module_id, "i-1234567890abcdef0-enc0123456789abcdef");
+        assert_eq!(doc.digest, Digest::SHA256);
+    }
+
+    #[test]
+    fn fail_every_nth() {
+        let attest = |nsm: &DevNitro| {
+            nsm.process_request(Request::Attestation {
+                user_data: None,
+                nonce: None,
+                public_key: None,
+            })
+        };
+
+        let nsm = builder().fail_with(ErrorCode::InternalError).build();
+        for _ in 0..3 {
+            assert!(matches!(
+                attest(&nsm),
+                Response::Error(ErrorCode::InternalError)
+            ));
+        }
+        assert!(matches!(
+            nsm.process_request(Request::DescribePCR { index: 0 }),
+            Response::DescribePCR { .. }
+        ));
+
+        let nsm = builder()
+            .fail_every_nth(3, ErrorCode::BufferTooSmall)
+            .build();
+        let failed = (0..6)
+            .map(|_| matches!(attest(&nsm), Response::Error(ErrorCode::BufferTooSmall)))
+            .collect::<Vec<bool>>();
+        assert_eq!(failed, [false, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn key_id() {
+        let key_id = |nsm: DevNitro| match nsm.process_request(Request::Attestation {
+            user_data: None,
+            nonce: None,
+            public_key: None,
+        }) {
+            Response::Attestation { document } => {
+                coset::CoseSign1::from_slice(&document)
+                    .unwrap()
+                    .protected
+                    .header
+                    .key_id
+            }
+            _ => panic!("Expected Attestation response"),
+        };
+
+        assert!(key_id(builder().build()).is_empty());
+        assert_eq!(
+            key_id(builder().key_id(b"dev-key".to_vec()).build()),
+            b"dev-key"
+        );
+    }
+
+    #[test]
+    fn builder_with_signer() {
+        use p384::ecdsa::{signature::Signer, signature::Verifier, Signature};
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let cert_chain =
+            nsm_nitro_enclave_utils_keygen::NsmCertChain::generate(Duration::from_secs(60));
+        let signing_key = cert_chain.end_signer.signing_key.as_p384().unwrap().clone();
+        let verifying_key = *signing_key.verifying_key();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let nsm = DevNitro::builder_with_signer(
+            coset::iana::Algorithm::ES384,
+            Box::new({
+                let calls = calls.clone();
+                move |bytes| {
+                    calls.fetch_add(1, Ordering::Relaxed);
+                    let signature: Signature = signing_key.sign(bytes);
+                    signature.to_bytes().to_vec()
+                }
+            }),
+            ByteBuf::from(cert_chain.end_signer.cert.to_der().unwrap()),
+        )
+        .build();
+
+        let document = match nsm.process_request(Request::Attestation {
+            user_data: None,
+            nonce: None,
+            public_key: None,
+        }) {
+            Response::Attestation { document } => document,
+            _ => panic!("Expected Attestation response"),
+        };
+
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        coset::CoseSign1::from_slice(&document)
+            .unwrap()
+            .verify_signature(b"", |signature, msg| {
+                verifying_key.verify(msg, &Signature::try_from(signature)?)
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn try_from_der_and_pem() {
+        use super::{DevNitroBuilder, ErrorKind};
+        use nsm_nitro_enclave_utils_keygen::PemEncodeExt;
+        use p384::pkcs8::{EncodePrivateKey, LineEnding};
+
+        let cert_chain =
+            nsm_nitro_enclave_utils_keygen::NsmCertChain::generate(Duration::from_secs(60));
+        let signing_key = cert_chain.end_signer.signing_key.as_p384().unwrap();
+        let end_cert = &cert_chain.end_signer.cert;
+
+        DevNitroBuilder::try_from_der(
+            signing_key.to_pkcs8_der().unwrap().as_bytes(),
+            &end_cert.to_der().unwrap(),
+        )
+        .unwrap()
+        .try_build()
+        .unwrap();
+        DevNitroBuilder::try_from_pem(
+            &signing_key.to_pkcs8_pem(LineEnding::LF).unwrap(),
+            &end_cert.to_pem(LineEnding::LF).unwrap(),
+        )
+        .unwrap()
+        .try_build()
+        .unwrap();
+
+        let err = DevNitroBuilder::try_from_der(b"not a key", &end_cert.to_der().unwrap())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), &ErrorKind::SigningKey);
+        let err = DevNitroBuilder::try_from_pem(
+            &signing_key.to_pkcs8_pem(LineEnding::LF).unwrap(),
+            "not a certificate",
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err.kind(), &ErrorKind::EndCertificate);
+    }
+
+    #[test]
+    fn try_build() {
+        use super::ErrorKind;
+
+        let cert_chain =
+            nsm_nitro_enclave_utils_keygen::NsmCertChain::generate(Duration::from_secs(60));
+        let signing_key = |signing_key: &nsm_nitro_enclave_utils_keygen::SigningKey| {
+            SecretKey::from(signing_key.as_p384().unwrap().as_nonzero_scalar())
+        };
+        let end_cert = ByteBuf::from(cert_chain.end_signer.cert.to_der().unwrap());
+
+        DevNitro::builder(
+            signing_key(&cert_chain.end_signer.signing_key),
+            end_cert.clone(),
+        )
+        .try_build()
+        .unwrap();
+
+        let int_signing_key = cert_chain.int_signer().signing_key.as_ref().unwrap();
+        let err = DevNitro::builder(signing_key(int_signing_key), end_cert)
+            .try_build()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), &ErrorKind::PublicKeyMismatch);
+
+        let err = DevNitro::builder(
+            signing_key(&cert_chain.end_signer.signing_key),
+            ByteBuf::from(b"not a certificate".to_vec()),
+        )
+        .try_build()
+        .err()
+        .unwrap();
+        assert_eq!(err.kind(), &ErrorKind::EndCertificate);
+
+        // Other curves sign with their own algorithm, as long as the end certificate is on the same curve
+        let p256_chain =
+            nsm_nitro_enclave_utils_keygen::NsmCertChain::builder(Duration::from_secs(60))
+                .key_algorithm(nsm_nitro_enclave_utils_keygen::KeyAlgorithm::P256)
+                .build()
+                .unwrap();
+        let p256_end_cert = ByteBuf::from(p256_chain.end_signer.cert.to_der().unwrap());
+        let document = DevNitro::builder(
+            p256_chain.end_signer.signing_key.as_p256().unwrap().clone(),
+            p256_end_cert.clone(),
+        )
+        .try_build()
+        .unwrap()
+        .attest(None, None, None)
+        .unwrap();
+        assert_eq!(
+            coset::CoseSign1::from_slice(&document)
+                .unwrap()
+                .protected
+                .header
+                .alg,
+            Some(coset::Algorithm::Assigned(coset::iana::Algorithm::ES256))
+        );
+
+        let err = DevNitro::builder(
+            signing_key(&cert_chain.end_signer.signing_key),
+            p256_end_cert,
+        )
+        .try_build()
+        .err()
+        .unwrap();
+        assert_eq!(err.kind(), &ErrorKind::EndCertificate);
     }
 }
@@ -1,9 +1,15 @@
 // Signing is only required in wasm targets for the wasm tests that include coverage for supporting attestation document verification in wasm.
 #[cfg_attr(target_arch = "wasm32", cfg(test))]
-pub(crate) mod sign;
+pub mod sign;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use sign::CoseSigningKey;
 
 #[cfg(not(target_arch = "wasm32"))]
 mod driver;
 
 #[cfg(not(target_arch = "wasm32"))]
-pub use driver::{DevNitro, DevNitroBuilder};
+pub use driver::{resign, DevNitro, DevNitroBuilder, DevNitroError, ErrorKind, DEFAULT_MODULE_ID};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::api::MAX_DOCUMENT_SIZE;
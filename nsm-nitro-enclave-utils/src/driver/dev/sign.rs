@@ -1,31 +1,238 @@
+//! Signs [`AttestationDoc`]s into COSE documents, for tests that need documents [`DevNitro`](super::DevNitro) doesn't produce.
+
 use aws_nitro_enclaves_nsm_api::api::AttestationDoc;
-use coset::{iana::Algorithm, CborSerializable, CoseSign1Builder, HeaderBuilder};
-use p384::ecdsa::{signature::Signer, Signature, SigningKey};
+use coset::{iana::Algorithm, CborSerializable, CoseSign1, CoseSign1Builder, HeaderBuilder};
+use p384::ecdsa::signature::Signer;
 use sealed::sealed;
 
 pub type SignCoseError = crate::Error<()>;
 
+/// A private key paired with the COSE algorithm it signs with, ensuring the protected header's `alg` always matches the curve.
+/// Authentic attestation documents are signed with ES384, the other algorithms are useful for testing that verifiers reject them.
+#[derive(Clone)]
+pub enum CoseSigningKey {
+    ES256(p256::ecdsa::SigningKey),
+    ES384(p384::ecdsa::SigningKey),
+    ES512(p521::ecdsa::SigningKey),
+}
+
+impl CoseSigningKey {
+    /// The COSE algorithm this key signs with, written to the protected header's `alg`
+    pub fn algorithm(&self) -> Algorithm {
+        match self {
+            Self::ES256(_) => Algorithm::ES256,
+            Self::ES384(_) => Algorithm::ES384,
+            Self::ES512(_) => Algorithm::ES512,
+        }
+    }
+
+    /// Produces the fixed size `r || s` signature expected by COSE
+    pub(crate) fn sign(&self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            Self::ES256(signing_key) => {
+                let signature: p256::ecdsa::Signature = signing_key.sign(bytes);
+                signature.to_bytes().to_vec()
+            }
+            Self::ES384(signing_key) => {
+                let signature: p384::ecdsa::Signature = signing_key.sign(bytes);
+                signature.to_bytes().to_vec()
+            }
+            Self::ES512(signing_key) => {
+                let signature: p521::ecdsa::Signature = signing_key.sign(bytes);
+                signature.to_bytes().to_vec()
+            }
+        }
+    }
+
+    /// Whether `public_key`, a SEC1 encoded point, is this key's public key.
+    /// Fails if `public_key` isn't a point on this key's curve.
+    pub(crate) fn matches_public_key(&self, public_key: &[u8]) -> Result<bool, p384::ecdsa::Error> {
+        Ok(match self {
+            Self::ES256(signing_key) => {
+                &p256::ecdsa::VerifyingKey::from_sec1_bytes(public_key)?
+                    == signing_key.verifying_key()
+            }
+            Self::ES384(signing_key) => {
+                &p384::ecdsa::VerifyingKey::from_sec1_bytes(public_key)?
+                    == signing_key.verifying_key()
+            }
+            Self::ES512(signing_key) => {
+                p521::ecdsa::VerifyingKey::from_sec1_bytes(public_key)?.to_encoded_point(false)
+                    == p521::ecdsa::VerifyingKey::from(signing_key).to_encoded_point(false)
+            }
+        })
+    }
+}
+
+impl From<p256::ecdsa::SigningKey> for CoseSigningKey {
+    fn from(signing_key: p256::ecdsa::SigningKey) -> Self {
+        Self::ES256(signing_key)
+    }
+}
+
+impl From<p384::ecdsa::SigningKey> for CoseSigningKey {
+    fn from(signing_key: p384::ecdsa::SigningKey) -> Self {
+        Self::ES384(signing_key)
+    }
+}
+
+impl From<p521::ecdsa::SigningKey> for CoseSigningKey {
+    fn from(signing_key: p521::ecdsa::SigningKey) -> Self {
+        Self::ES512(signing_key)
+    }
+}
+
+impl From<p384::SecretKey> for CoseSigningKey {
+    fn from(secret_key: p384::SecretKey) -> Self {
+        Self::ES384(secret_key.into())
+    }
+}
+
+/// Signs an [`AttestationDoc`], i.e. one built with [`AttestationDocBuilder`](crate::api::AttestationDocBuilder)
 #[sealed]
-pub(crate) trait AttestationDocSignerExt {
-    fn sign(&self, signing_key: SigningKey) -> Result<Vec<u8>, SignCoseError>;
+pub trait AttestationDocSignerExt {
+    /// Signs the attestation document, using the algorithm matching `signing_key`'s curve
+    fn sign(&self, signing_key: impl Into<CoseSigningKey>) -> Result<Vec<u8>, SignCoseError>;
+
+    /// Behaves like [`AttestationDocSignerExt::sign`], returning the [`CoseSign1`] before it's serialized.
+    /// Useful for inspecting or tampering with the COSE structure.
+    /// ```rust
+    /// use nsm_nitro_enclave_utils::api::{AttestationDocBuilder, ByteBuf};
+    /// use nsm_nitro_enclave_utils::driver::dev::sign::AttestationDocSignerExt;
+    ///
+    /// let signing_key = p384::ecdsa::SigningKey::random(&mut rand::thread_rng());
+    /// let mut cose = AttestationDocBuilder::new(ByteBuf::new())
+    ///     .build()
+    ///     .sign_to_cose(signing_key)
+    ///     .unwrap();
+    /// cose.unprotected.key_id = b"unprotected".to_vec();
+    /// ```
+    fn sign_to_cose(
+        &self,
+        signing_key: impl Into<CoseSigningKey>,
+    ) -> Result<CoseSign1, SignCoseError>;
+
+    /// Behaves like [`AttestationDocSignerExt::sign`], flipping a bit in the signature so it no longer matches the payload.
+    /// Only intended for tests: the certificate chain and payload remain valid, so verification fails on the signature check alone.
+    /// ```rust
+    /// use coset::{CborSerializable, CoseSign1};
+    /// use nsm_nitro_enclave_utils::api::{AttestationDocBuilder, ByteBuf};
+    /// use nsm_nitro_enclave_utils::driver::dev::sign::AttestationDocSignerExt;
+    ///
+    /// let signing_key = p384::ecdsa::SigningKey::random(&mut rand::thread_rng());
+    /// let doc = AttestationDocBuilder::new(ByteBuf::new()).build();
+    /// let valid = CoseSign1::from_slice(&doc.sign(signing_key.clone()).unwrap()).unwrap();
+    /// let corrupted = CoseSign1::from_slice(&doc.sign_corrupted(signing_key).unwrap()).unwrap();
+    /// assert_eq!(valid.payload, corrupted.payload);
+    /// assert_ne!(valid.signature, corrupted.signature);
+    /// ```
+    fn sign_corrupted(
+        &self,
+        signing_key: impl Into<CoseSigningKey>,
+    ) -> Result<Vec<u8>, SignCoseError>;
+
+    /// Behaves like [`AttestationDocSignerExt::sign`], additionally setting the COSE `kid` protected header to `kid`.
+    /// Verifiers can use the `kid` to select among multiple keys.
+    /// ```rust
+    /// use coset::{CborSerializable, CoseSign1};
+    /// use nsm_nitro_enclave_utils::api::{AttestationDocBuilder, ByteBuf};
+    /// use nsm_nitro_enclave_utils::driver::dev::sign::AttestationDocSignerExt;
+    ///
+    /// let signing_key = p384::ecdsa::SigningKey::random(&mut rand::thread_rng());
+    /// let document = AttestationDocBuilder::new(ByteBuf::new())
+    ///     .build()
+    ///     .sign_with_kid(signing_key, b"dev-key")
+    ///     .unwrap();
+    /// let cose = CoseSign1::from_slice(&document).unwrap();
+    /// assert_eq!(cose.protected.header.key_id, b"dev-key");
+    /// ```
+    fn sign_with_kid(
+        &self,
+        signing_key: impl Into<CoseSigningKey>,
+        kid: &[u8],
+    ) -> Result<Vec<u8>, SignCoseError>;
+
+    /// Signs the attestation document with an external signer, i.e. a key held by an HSM or KMS.
+    /// `sign_fn` receives the COSE `Sig_structure` bytes and returns the raw signature, as produced by `alg`.
+    /// The `kid` protected header is set to `kid`, unless it's empty.
+    fn sign_with<F>(
+        &self,
+        alg: Algorithm,
+        kid: &[u8],
+        sign_fn: F,
+    ) -> Result<Vec<u8>, SignCoseError>
+    where
+        F: FnMut(&[u8]) -> Vec<u8>;
 }
 
 #[sealed]
 impl AttestationDocSignerExt for AttestationDoc {
-    fn sign(&self, signing_key: SigningKey) -> Result<Vec<u8>, SignCoseError> {
-        let headers = HeaderBuilder::new().algorithm(Algorithm::ES384).build();
+    fn sign(&self, signing_key: impl Into<CoseSigningKey>) -> Result<Vec<u8>, SignCoseError> {
+        self.sign_to_cose(signing_key)?
+            .to_vec()
+            .map_err(|err| SignCoseError::new((), err))
+    }
 
-        let payload = self.to_binary();
+    fn sign_to_cose(
+        &self,
+        signing_key: impl Into<CoseSigningKey>,
+    ) -> Result<CoseSign1, SignCoseError> {
+        let signing_key = signing_key.into();
+        Ok(cose_sign1(self, signing_key.algorithm(), &[], |bytes| {
+            signing_key.sign(bytes)
+        }))
+    }
 
-        let cose = CoseSign1Builder::new()
-            .payload(payload)
-            .protected(headers)
-            .create_signature(b"", |bytes| {
-                let signature: Signature = signing_key.sign(bytes);
-                signature.to_bytes().to_vec()
-            })
-            .build();
+    fn sign_corrupted(
+        &self,
+        signing_key: impl Into<CoseSigningKey>,
+    ) -> Result<Vec<u8>, SignCoseError> {
+        let mut cose_sign1 = self.sign_to_cose(signing_key)?;
+        if let Some(byte) = cose_sign1.signature.first_mut() {
+            *byte ^= 0x01;
+        }
+
+        cose_sign1
+            .to_vec()
+            .map_err(|err| SignCoseError::new((), err))
+    }
+
+    fn sign_with_kid(
+        &self,
+        signing_key: impl Into<CoseSigningKey>,
+        kid: &[u8],
+    ) -> Result<Vec<u8>, SignCoseError> {
+        let signing_key = signing_key.into();
+        self.sign_with(signing_key.algorithm(), kid, |bytes| {
+            signing_key.sign(bytes)
+        })
+    }
 
-        cose.to_vec().map_err(|err| SignCoseError::new((), err))
+    fn sign_with<F>(&self, alg: Algorithm, kid: &[u8], sign_fn: F) -> Result<Vec<u8>, SignCoseError>
+    where
+        F: FnMut(&[u8]) -> Vec<u8>,
+    {
+        cose_sign1(self, alg, kid, sign_fn)
+            .to_vec()
+            .map_err(|err| SignCoseError::new((), err))
     }
 }
+
+fn cose_sign1<F>(doc: &AttestationDoc, alg: Algorithm, kid: &[u8], sign_fn: F) -> CoseSign1
+where
+    F: FnMut(&[u8]) -> Vec<u8>,
+{
+    // An empty `kid` is omitted from the encoded header
+    let headers = HeaderBuilder::new()
+        .algorithm(alg)
+        .key_id(kid.to_vec())
+        .build();
+
+    let payload = doc.to_binary();
+
+    CoseSign1Builder::new()
+        .payload(payload)
+        .protected(headers)
+        .create_signature(b"", sign_fn)
+        .build()
+}
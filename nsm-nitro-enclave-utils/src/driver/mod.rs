@@ -2,10 +2,10 @@
 //!
 //! ### Features
 //! * **nitro** -
-//! Enabled by default. Enables the [`nitro`] module, which contains the [`nitro::Nitro`] struct. It makes authentic requests to the Nitro Secure Module, and only works inside an authentic Nitro Enclave.
+//!   Enabled by default. Enables the [`nitro`] module, which contains the [`nitro::Nitro`] struct. It makes authentic requests to the Nitro Secure Module, and only works inside an authentic Nitro Enclave.
 //!
 //! * **pki** -
-//! When enabled, the [`dev`] module is included, which contains the [`dev::DevNitro`] struct. It is designed mimic responses from an authentic Nitro Secure Module, allowing you to build for Nitro Enclaves locally.
+//!   When enabled, the [`dev`] module is included, which contains the [`dev::DevNitro`] struct. It is designed mimic responses from an authentic Nitro Secure Module, allowing you to build for Nitro Enclaves locally.
 
 #[cfg(feature = "pki")]
 pub mod dev;
@@ -13,6 +13,15 @@ pub mod dev;
 #[cfg(feature = "nitro")]
 pub mod nitro;
 
+mod capture;
+pub use capture::capture_attestation;
+
+mod recording;
+pub use recording::RecordingDriver;
+
+mod asynchronous;
+pub use asynchronous::{AsyncDriver, AsyncNsm, SyncDriver};
+
 use crate::api::nsm::{Request, Response};
 
 /// [`Driver`] is a simple trait meant to conform to aws-nitro-enclaves-nsm-api's api interface.
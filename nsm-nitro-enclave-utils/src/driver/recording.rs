@@ -0,0 +1,140 @@
+use crate::api::nsm::{Request, Response};
+use crate::driver::Driver;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// [`RecordingDriver`] wraps another [`Driver`], recording every [`Request`] before delegating it to the inner driver.
+/// Useful for asserting which requests your code made, i.e. that a nonce was forwarded to the Nitro Secure Module.
+/// ```rust
+/// use nsm_nitro_enclave_utils::{driver::{Driver, RecordingDriver}, api::nsm::{Request, Response, ErrorCode}};
+///
+/// struct Unavailable;
+/// impl Driver for Unavailable {
+///     fn process_request(&self, _: Request) -> Response {
+///         Response::Error(ErrorCode::InvalidOperation)
+///     }
+/// }
+///
+/// let nsm = RecordingDriver::new(Unavailable);
+/// let requests = nsm.requests();
+///
+/// nsm.process_request(Request::DescribePCR { index: 0 });
+///
+/// assert!(matches!(requests.lock().unwrap()[..], [Request::DescribePCR { index: 0 }]));
+/// ```
+pub struct RecordingDriver<D> {
+    inner: D,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl<D: Driver> RecordingDriver<D> {
+    pub fn new(inner: D) -> Self {
+        Self {
+            inner,
+            requests: Arc::default(),
+        }
+    }
+
+    /// A handle to the recorded requests, in the order they were made.
+    /// The handle remains usable after the [`RecordingDriver`] has been moved, i.e. into a `Box<dyn Driver>`.
+    pub fn requests(&self) -> Arc<Mutex<Vec<Request>>> {
+        self.requests.clone()
+    }
+}
+
+impl<D: Driver> Driver for RecordingDriver<D> {
+    fn process_request(&self, request: Request) -> Response {
+        if let Some(copy) = copy_request(&request) {
+            self.requests
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(copy);
+        }
+        self.inner.process_request(request)
+    }
+}
+
+/// [`Request`] doesn't implement `Clone`.
+/// It's `#[non_exhaustive]`, variants added by future versions of `aws-nitro-enclaves-nsm-api` aren't copied and go unrecorded.
+fn copy_request(request: &Request) -> Option<Request> {
+    let copy = match request {
+        Request::DescribePCR { index } => Request::DescribePCR { index: *index },
+        Request::ExtendPCR { index, data } => Request::ExtendPCR {
+            index: *index,
+            data: data.clone(),
+        },
+        Request::LockPCR { index } => Request::LockPCR { index: *index },
+        Request::LockPCRs { range } => Request::LockPCRs { range: *range },
+        Request::DescribeNSM => Request::DescribeNSM,
+        Request::Attestation {
+            user_data,
+            nonce,
+            public_key,
+        } => Request::Attestation {
+            user_data: user_data.clone(),
+            nonce: nonce.clone(),
+            public_key: public_key.clone(),
+        },
+        Request::GetRandom => Request::GetRandom,
+        _ => return None,
+    };
+    Some(copy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RecordingDriver;
+    use crate::api::{
+        nsm::{ErrorCode, Request, Response},
+        ByteBuf,
+    };
+    use crate::driver::Driver;
+
+    struct Unavailable;
+
+    impl Driver for Unavailable {
+        fn process_request(&self, _: Request) -> Response {
+            Response::Error(ErrorCode::InvalidOperation)
+        }
+    }
+
+    #[test]
+    fn records_requests() {
+        let nsm = RecordingDriver::new(Unavailable);
+        let requests = nsm.requests();
+        assert!(requests.lock().unwrap().is_empty());
+
+        // The handle outlives the driver being moved
+        let nsm: Box<dyn Driver> = Box::new(nsm);
+        nsm.process_request(Request::DescribePCR { index: 3 });
+        assert!(matches!(
+            nsm.process_request(Request::Attestation {
+                user_data: None,
+                nonce: Some(ByteBuf::from(b"nonce".to_vec())),
+                public_key: None,
+            }),
+            Response::Error(ErrorCode::InvalidOperation)
+        ));
+
+        nsm.process_request(Request::ExtendPCR {
+            index: 16,
+            data: b"data".to_vec(),
+        });
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(matches!(requests[0], Request::DescribePCR { index: 3 }));
+        match &requests[1] {
+            Request::Attestation { nonce, .. } => {
+                assert_eq!(nonce.as_ref().unwrap().as_slice(), b"nonce")
+            }
+            _ => panic!("Expected Attestation request"),
+        }
+        match &requests[2] {
+            Request::ExtendPCR { index, data } => {
+                assert_eq!(*index, 16);
+                assert_eq!(data, b"data");
+            }
+            _ => panic!("Expected ExtendPCR request"),
+        }
+    }
+}
@@ -6,6 +6,10 @@ pub mod driver;
 
 pub mod time;
 
+pub mod nonce;
+
+pub mod attestation_doc;
+
 pub mod pcr;
 
 #[cfg(feature = "verify")]
@@ -18,8 +22,8 @@ pub mod verify;
 /// If the error returned from the underlying library does not implement [`std::error::Error`], or the error originated due to this library's own assertions, [`ErrorContext`] is used.
 pub struct Error<T> {
     kind: T,
-    _backtrace: std::backtrace::Backtrace,
-    _source: Box<dyn std::error::Error + Send + Sync>,
+    backtrace: std::backtrace::Backtrace,
+    source: Box<dyn std::error::Error + Send + Sync>,
 }
 
 impl<T> Error<T> {
@@ -29,14 +33,38 @@ impl<T> Error<T> {
     {
         Self {
             kind,
-            _backtrace: std::backtrace::Backtrace::capture(),
-            _source: Box::new(err),
+            backtrace: std::backtrace::Backtrace::capture(),
+            source: Box::new(err),
         }
     }
 
     pub fn kind(&self) -> &T {
         &self.kind
     }
+
+    /// The backtrace captured when the error occurred.
+    /// Only captured when enabled via the `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` environment variables, see [`std::backtrace::Backtrace::capture`].
+    pub fn backtrace(&self) -> &std::backtrace::Backtrace {
+        &self.backtrace
+    }
+
+    /// The underlying error that caused the failure, also available via [`std::error::Error::source`]
+    pub fn source_err(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        self.source.as_ref()
+    }
+}
+
+/// Displays the `kind`, followed by the `source`
+impl<T: std::fmt::Debug> std::fmt::Display for Error<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}", self.kind, self.source)
+    }
+}
+
+impl<T: std::fmt::Debug> std::error::Error for Error<T> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
 }
 
 /// Used by errors to provide additional context if the error returned from the underlying library does not implement [`std::error::Error`],
@@ -52,6 +80,30 @@ impl std::fmt::Display for ErrorContext {
 
 impl std::error::Error for ErrorContext {}
 
+#[cfg(test)]
+mod tests {
+    use super::{Error, ErrorContext};
+
+    #[test]
+    fn display_and_source() {
+        #[derive(Debug)]
+        enum Kind {
+            Nonce,
+        }
+
+        let err = Error::new(Kind::Nonce, ErrorContext("Nonce mismatch"));
+        assert_eq!(err.to_string(), "Nonce: Nonce mismatch");
+        assert_eq!(err.source_err().to_string(), "Nonce mismatch");
+        assert!(err
+            .source_err()
+            .downcast_ref::<ErrorContext>()
+            .is_some_and(|context| context.0 == "Nonce mismatch"));
+
+        let err: Box<dyn std::error::Error> = Box::new(err);
+        assert_eq!(err.source().unwrap().to_string(), "Nonce mismatch");
+    }
+}
+
 #[cfg(all(test, target_arch = "wasm32"))]
 /// This test suite is expected to reasonable cover all features that WebAssembly support.
 /// See README for instructions for running these tests.
@@ -69,7 +121,7 @@ mod wasm_tests {
         use crate::time::Time;
         use crate::verify::AttestationDocVerifierExt;
 
-        let time = Time::new(Box::new(|| include!("../../test_data/created_at.txt")));
+        let time = Time::fixed(include!("../../test_data/created_at.txt"));
 
         let root_cert = include_bytes!("../../test_data/root-certificate.der");
         let int_cert = include_bytes!("../../test_data/int-certificate.der");
@@ -120,6 +172,29 @@ mod wasm_tests {
         assert_ne!(a, c);
     }
 
+    #[cfg(feature = "extend")]
+    #[wasm_bindgen_test]
+    fn measure() {
+        use crate::pcr::{PcrIndex, Pcrs};
+
+        let mut a = Pcrs::zeros();
+        a.measure(PcrIndex::Zero, b"kernel".as_slice()).unwrap();
+        let mut b = Pcrs::zeros();
+        b.measure(PcrIndex::Zero, b"kernel".as_slice()).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, Pcrs::zeros());
+    }
+
+    #[cfg(feature = "js-date")]
+    #[wasm_bindgen_test]
+    fn js_date() {
+        use crate::time::Time;
+
+        // 2024-01-01T00:00:00Z
+        assert!(Time::js_date().time() > 1_704_067_200_000);
+        assert!(Time::default().time() > 1_704_067_200_000);
+    }
+
     #[cfg(feature = "rand")]
     #[wasm_bindgen_test]
     fn rand() {
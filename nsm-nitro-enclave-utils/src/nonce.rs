@@ -0,0 +1,104 @@
+use crate::api::ByteBuf;
+use crate::ErrorContext;
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// Returned when a [`Nonce`] can't be decoded, or has an invalid length
+pub type NonceError = crate::Error<()>;
+
+/// The maximum length of the nonce accepted by the Nitro Secure Module in an `Attestation` request
+pub const MAX_NONCE_LENGTH: usize = 512;
+
+/// A nonce included in an `Attestation` request, and expected in the returned attestation document.
+/// Ties together the encodings the nonce is transported in, ensuring the same bytes are requested and verified.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Nonce(Vec<u8>);
+
+impl Nonce {
+    /// Fails if `bytes` is empty or longer than [`MAX_NONCE_LENGTH`]
+    pub fn new(bytes: Vec<u8>) -> Result<Self, NonceError> {
+        if bytes.is_empty() || bytes.len() > MAX_NONCE_LENGTH {
+            return Err(NonceError::new(
+                (),
+                ErrorContext("A nonce must have a length between 1 and 512 bytes"),
+            ));
+        }
+
+        Ok(Self(bytes))
+    }
+
+    /// Decodes a hex encoded nonce
+    pub fn from_hex(s: &str) -> Result<Self, NonceError> {
+        let bytes = hex::decode(s).map_err(|err| NonceError::new((), err))?;
+        Self::new(bytes)
+    }
+
+    /// Decodes a nonce using standard, padded, base64
+    pub fn from_base64(s: &str) -> Result<Self, NonceError> {
+        let bytes = STANDARD.decode(s).map_err(|err| NonceError::new((), err))?;
+        Self::new(bytes)
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.0)
+    }
+
+    /// Encodes the nonce using standard, padded, base64
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(&self.0)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Nonce {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl TryFrom<Vec<u8>> for Nonce {
+    type Error = NonceError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        Self::new(bytes)
+    }
+}
+
+impl From<Nonce> for Vec<u8> {
+    fn from(nonce: Nonce) -> Self {
+        nonce.0
+    }
+}
+
+/// The representation used by `Request::Attestation`
+impl From<Nonce> for ByteBuf {
+    fn from(nonce: Nonce) -> Self {
+        ByteBuf::from(nonce.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Nonce, MAX_NONCE_LENGTH};
+
+    #[test]
+    fn encodings() {
+        let nonce = Nonce::new(b"nonce".to_vec()).unwrap();
+        assert_eq!(nonce.to_hex(), "6e6f6e6365");
+        assert_eq!(nonce.to_base64(), "bm9uY2U=");
+        assert_eq!(Nonce::from_hex(&nonce.to_hex()).unwrap(), nonce);
+        assert_eq!(Nonce::from_base64(&nonce.to_base64()).unwrap(), nonce);
+
+        assert!(Nonce::from_hex("not hex").is_err());
+        assert!(Nonce::from_base64("not base64!").is_err());
+    }
+
+    #[test]
+    fn length() {
+        assert!(Nonce::new(Vec::new()).is_err());
+        assert!(Nonce::new(vec![0; MAX_NONCE_LENGTH]).is_ok());
+        assert!(Nonce::new(vec![0; MAX_NONCE_LENGTH + 1]).is_err());
+    }
+}
@@ -2,13 +2,17 @@
 //! The primary goal here is to achieve stronger type safety when working with PCRs, as well as provide abstractions to generate [`Pcrs`] that you can use when self-signing your own attestation documents.
 //!
 //! [`Pcr`] wraps a single Platform Configuration Register, allowing for stronger type safety across your application.
-//! [`Pcrs`] wraps all 6 Platform Configuration Registers returned by the Nitro Secure Module. Each can be infallibly accessed via [`Pcrs::get`].
+//! [`Pcrs`] wraps the Platform Configuration Registers returned by the Nitro Secure Module: 0 through 15, plus any of 16 through 31 that hold custom measurements. Each can be infallibly accessed via [`Pcrs::get`].
 //! [`Pcrs`] also provides several methods that allow you to initial a collection of Platform Configuration Registers when self-signing attestation documents, some of which require additional feature flags.
 
 use crate::ErrorContext;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use serde_bytes::ByteBuf;
 use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
 use std::ops::Deref;
+use std::str::FromStr;
+use subtle::ConstantTimeEq;
 
 /// A [`Pcr`] failed to pass length checks.
 pub type PcrLengthError = crate::Error<()>;
@@ -16,38 +20,126 @@ pub type PcrLengthError = crate::Error<()>;
 /// [`Pcrs`] included an [invalid index](https://docs.aws.amazon.com/enclaves/latest/user/set-up-attestation.html#where)
 pub type PcrIndexError = crate::Error<()>;
 
-/// The Nitro Secure Module returns PCRs 0 through 8, with some missing.
-/// https://docs.aws.amazon.com/enclaves/latest/user/set-up-attestation.html#where
-pub(crate) const PCR_INDEXES: [PcrIndex; 6] = [
+/// [`Pcrs::try_from_complete`] was missing some of PCRs 0 through 15
+pub type IncompletePcrsError = crate::Error<()>;
+
+/// A [`Pcr`] didn't belong to the same [`PcrDigest`] bank as the other [`Pcrs`]
+pub type PcrDigestError = crate::Error<()>;
+
+/// The Nitro Secure Module reports PCRs 0 through 15 in every attestation document, some of which are [measured by AWS](https://docs.aws.amazon.com/enclaves/latest/user/set-up-attestation.html#where).
+/// Every [`Pcrs`] contains these indexes.
+pub(crate) const PCR_INDEXES: [PcrIndex; 16] = [
+    PcrIndex::Zero,
+    PcrIndex::One,
+    PcrIndex::Two,
+    PcrIndex::Three,
+    PcrIndex::Four,
+    PcrIndex::Five,
+    PcrIndex::Six,
+    PcrIndex::Seven,
+    PcrIndex::Eight,
+    PcrIndex::Nine,
+    PcrIndex::Ten,
+    PcrIndex::Eleven,
+    PcrIndex::Twelve,
+    PcrIndex::Thirteen,
+    PcrIndex::Fourteen,
+    PcrIndex::Fifteen,
+];
+
+/// Every PCR index supported by the Nitro Secure Module, including 16 through 31 which are reserved for custom measurements.
+const ALL_PCR_INDEXES: [PcrIndex; 32] = [
     PcrIndex::Zero,
     PcrIndex::One,
     PcrIndex::Two,
     PcrIndex::Three,
     PcrIndex::Four,
+    PcrIndex::Five,
+    PcrIndex::Six,
+    PcrIndex::Seven,
     PcrIndex::Eight,
+    PcrIndex::Nine,
+    PcrIndex::Ten,
+    PcrIndex::Eleven,
+    PcrIndex::Twelve,
+    PcrIndex::Thirteen,
+    PcrIndex::Fourteen,
+    PcrIndex::Fifteen,
+    PcrIndex::Sixteen,
+    PcrIndex::Seventeen,
+    PcrIndex::Eighteen,
+    PcrIndex::Nineteen,
+    PcrIndex::Twenty,
+    PcrIndex::TwentyOne,
+    PcrIndex::TwentyTwo,
+    PcrIndex::TwentyThree,
+    PcrIndex::TwentyFour,
+    PcrIndex::TwentyFive,
+    PcrIndex::TwentySix,
+    PcrIndex::TwentySeven,
+    PcrIndex::TwentyEight,
+    PcrIndex::TwentyNine,
+    PcrIndex::Thirty,
+    PcrIndex::ThirtyOne,
 ];
 
-/// An enum that corresponds to the valid PCR indexes. Used to ensure PCR related operations that are infallible can remain infallible.
+/// An enum that corresponds to the valid PCR indexes of the Nitro Secure Module. Used to ensure PCR related operations that are infallible can remain infallible.
+/// See [AWS's documentation](https://docs.aws.amazon.com/enclaves/latest/user/set-up-attestation.html#where) for the indexes AWS measures.
 #[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Eq, Ord)]
 pub enum PcrIndex {
-    Zero,
-    One,
-    Two,
-    Three,
-    Four,
-    Eight,
+    Zero = 0,
+    One = 1,
+    Two = 2,
+    Three = 3,
+    Four = 4,
+    Five = 5,
+    Six = 6,
+    Seven = 7,
+    Eight = 8,
+    Nine = 9,
+    Ten = 10,
+    Eleven = 11,
+    Twelve = 12,
+    Thirteen = 13,
+    Fourteen = 14,
+    Fifteen = 15,
+    Sixteen = 16,
+    Seventeen = 17,
+    Eighteen = 18,
+    Nineteen = 19,
+    Twenty = 20,
+    TwentyOne = 21,
+    TwentyTwo = 22,
+    TwentyThree = 23,
+    TwentyFour = 24,
+    TwentyFive = 25,
+    TwentySix = 26,
+    TwentySeven = 27,
+    TwentyEight = 28,
+    TwentyNine = 29,
+    Thirty = 30,
+    ThirtyOne = 31,
+}
+
+impl PcrIndex {
+    /// Every valid index, 0 through 31, in ascending order.
+    ///
+    /// AWS only measures PCRs 0 through 4 and 8, but the Nitro Secure Module reports 0 through 15 in every attestation document,
+    /// the unmeasured ones as zeros, and supports extending 16 through 31 with custom measurements. None of them are excluded,
+    /// allowing every index of an attestation document's PCR map to be represented.
+    pub fn all() -> &'static [PcrIndex] {
+        &ALL_PCR_INDEXES
+    }
+
+    /// Whether `index` is a valid PCR index, in which case [`PcrIndex::try_from`] succeeds
+    pub fn is_valid(index: usize) -> bool {
+        index < ALL_PCR_INDEXES.len()
+    }
 }
 
 impl From<PcrIndex> for usize {
     fn from(index: PcrIndex) -> Self {
-        match index {
-            PcrIndex::Zero => 0,
-            PcrIndex::One => 1,
-            PcrIndex::Two => 2,
-            PcrIndex::Three => 3,
-            PcrIndex::Four => 4,
-            PcrIndex::Eight => 8,
-        }
+        index as usize
     }
 }
 
@@ -55,33 +147,69 @@ impl TryFrom<usize> for PcrIndex {
     type Error = PcrIndexError;
 
     fn try_from(index: usize) -> Result<Self, Self::Error> {
-        match index {
-            0 => Ok(PcrIndex::Zero),
-            1 => Ok(PcrIndex::One),
-            2 => Ok(PcrIndex::Two),
-            3 => Ok(PcrIndex::Three),
-            4 => Ok(PcrIndex::Four),
-            8 => Ok(PcrIndex::Eight),
-            _ => Err(PcrIndexError::new(
-                (),
-                ErrorContext("Invalid PCR index provided"),
-            )),
-        }
+        ALL_PCR_INDEXES
+            .get(index)
+            .copied()
+            .ok_or_else(|| PcrIndexError::new((), ErrorContext("Invalid PCR index provided")))
     }
 }
 
 /// Sha384 hashes contain 48 bytes
 const PCR_LENGTH: usize = 48;
 
-/// Platform Configuration Register
+/// Sha256 hashes contain 32 bytes
+const SHA256_PCR_LENGTH: usize = 32;
+
+/// The hash algorithm of a PCR bank, which determines the length of each [`Pcr`].
+/// Authentic attestation documents use [`PcrDigest::Sha384`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PcrDigest {
+    Sha256,
+    #[default]
+    Sha384,
+}
+
+impl PcrDigest {
+    /// The length of a [`Pcr`] in this bank, in bytes
+    pub fn pcr_length(&self) -> usize {
+        match self {
+            PcrDigest::Sha256 => SHA256_PCR_LENGTH,
+            PcrDigest::Sha384 => PCR_LENGTH,
+        }
+    }
+}
+
+/// Used to populate [`aws_nitro_enclaves_nsm_api::api::AttestationDoc`]'s `digest`
+impl From<PcrDigest> for aws_nitro_enclaves_nsm_api::api::Digest {
+    fn from(digest: PcrDigest) -> Self {
+        match digest {
+            PcrDigest::Sha256 => aws_nitro_enclaves_nsm_api::api::Digest::SHA256,
+            PcrDigest::Sha384 => aws_nitro_enclaves_nsm_api::api::Digest::SHA384,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum PcrValue {
+    Sha256([u8; SHA256_PCR_LENGTH]),
+    Sha384([u8; PCR_LENGTH]),
+}
+
+/// Platform Configuration Register. Contains either a SHA384 (48 bytes) or SHA256 (32 bytes) digest, see [`PcrDigest`].
+///
+/// The derived [`PartialEq`] short-circuits on the first differing byte. It's convenient in tests, but security-sensitive code,
+/// i.e. code that gates access on a PCR matching an expected value, should compare with [`Pcr::ct_eq`] instead.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Pcr([u8; PCR_LENGTH]);
+pub struct Pcr(PcrValue);
 
 impl Deref for Pcr {
-    type Target = [u8; PCR_LENGTH];
+    type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        match &self.0 {
+            PcrValue::Sha256(bytes) => bytes,
+            PcrValue::Sha384(bytes) => bytes,
+        }
     }
 }
 
@@ -89,17 +217,97 @@ impl TryFrom<Vec<u8>> for Pcr {
     type Error = PcrLengthError;
 
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
-        let inner = value.try_into().map_err(|_| {
-            PcrLengthError::new((), ErrorContext("A PCR must have a length of 48."))
-        })?;
-
-        Ok(Self(inner))
+        match value.len() {
+            SHA256_PCR_LENGTH => Ok(Self(PcrValue::Sha256(
+                value.try_into().expect("Length was checked"),
+            ))),
+            PCR_LENGTH => Ok(Self(PcrValue::Sha384(
+                value.try_into().expect("Length was checked"),
+            ))),
+            _ => Err(PcrLengthError::new(
+                (),
+                ErrorContext("A PCR must have a length of 48, or 32 when using a SHA256 bank."),
+            )),
+        }
     }
 }
 
 impl From<[u8; PCR_LENGTH]> for Pcr {
     fn from(inner: [u8; PCR_LENGTH]) -> Self {
-        Self(inner)
+        Self(PcrValue::Sha384(inner))
+    }
+}
+
+impl From<[u8; SHA256_PCR_LENGTH]> for Pcr {
+    fn from(inner: [u8; SHA256_PCR_LENGTH]) -> Self {
+        Self(PcrValue::Sha256(inner))
+    }
+}
+
+impl Pcr {
+    /// A [`Pcr`] of all zeros, with the length of the provided [`PcrDigest`]
+    pub fn zeros(digest: PcrDigest) -> Self {
+        match digest {
+            PcrDigest::Sha256 => [0; SHA256_PCR_LENGTH].into(),
+            PcrDigest::Sha384 => [0; PCR_LENGTH].into(),
+        }
+    }
+
+    /// The [`PcrDigest`] bank this [`Pcr`] belongs to, determined by its length
+    pub fn digest(&self) -> PcrDigest {
+        match self.0 {
+            PcrValue::Sha256(_) => PcrDigest::Sha256,
+            PcrValue::Sha384(_) => PcrDigest::Sha384,
+        }
+    }
+
+    /// Compares two [`Pcr`]s in constant time, avoiding timing side channels when comparing against expected values.
+    /// [`Pcr`]s from different [`PcrDigest`] banks are never equal.
+    pub fn ct_eq(&self, other: &Pcr) -> bool {
+        (**self).ct_eq(&**other).into()
+    }
+}
+
+/// Parses a hex encoded [`Pcr`], as presented by the AWS CLI, i.e. 96 hex characters, or 64 for a SHA256 bank.
+impl FromStr for Pcr {
+    type Err = PcrLengthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = hex::decode(s).map_err(|err| PcrLengthError::new((), err))?;
+        Pcr::try_from(bytes)
+    }
+}
+
+/// Writes the [`Pcr`] as lowercase hex, matching how AWS presents PCRs.
+impl Display for Pcr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", hex::encode(&**self))
+    }
+}
+
+/// Serializes the [`Pcr`] as a lowercase hex string
+impl Serialize for Pcr {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// Deserializes a [`Pcr`] from a hex string, rejecting values that aren't 48 or 32 bytes long
+impl<'de> Deserialize<'de> for Pcr {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let hex = String::deserialize(deserializer)?;
+        hex.parse().map_err(|_| {
+            de::Error::invalid_value(
+                de::Unexpected::Str(&hex),
+                &"a hex encoded PCR with a length of 48 or 32 bytes",
+            )
+        })
     }
 }
 
@@ -125,28 +333,196 @@ impl Pcrs {
         }
         Self(pcrs)
     }
+
+    /// Creates a new `Pcrs` from the provided values, in the [`PcrDigest`] bank of the first of them, SHA384 if there are none.
+    /// Omitted PCRs 0 through 15 are zeros of that bank. Fails if any value belongs to another bank.
+    fn from_values<I>(values: I) -> Result<Self, PcrDigestError>
+    where
+        I: IntoIterator<Item = (PcrIndex, Pcr)>,
+    {
+        let mut values = values.into_iter().peekable();
+        let digest = values
+            .peek()
+            .map(|(_, pcr)| pcr.digest())
+            .unwrap_or_default();
+        let mut pcrs = Pcrs::zeros_with_digest(digest);
+        for (index, pcr) in values {
+            pcrs.set(index, pcr)?;
+        }
+
+        Ok(pcrs)
+    }
 }
 
 /// Useful if you have pre-generated PCRs you wish to mock.
 /// If you don't already have PCRs, you should probably use [`Pcrs`]'s methods to generate what you need.
-/// If a given [`PcrIndex`] is omitted in the BTreeMap, it will be replaced with all zeros.
-impl From<BTreeMap<PcrIndex, Pcr>> for Pcrs {
-    fn from(values: BTreeMap<PcrIndex, Pcr>) -> Self {
-        let mut pcrs = Pcrs::zeros();
-        for (index, pcr) in values {
-            pcrs.set(index, pcr);
+/// If a given [`PcrIndex`] is omitted in the BTreeMap, it will be replaced with all zeros of the provided PCRs' [`PcrDigest`] bank.
+/// Fails if the provided PCRs don't all belong to the same bank.
+impl TryFrom<BTreeMap<PcrIndex, Pcr>> for Pcrs {
+    type Error = PcrDigestError;
+
+    fn try_from(values: BTreeMap<PcrIndex, Pcr>) -> Result<Self, Self::Error> {
+        Pcrs::from_values(values)
+    }
+}
+
+impl Pcrs {
+    /// Behaves like `Pcrs::try_from(values)`, failing rather than replacing PCRs 0 through 15 with zeros if any are missing from `values`.
+    /// Useful to ensure a full set of PCRs was provided, i.e. when they are loaded from a file.
+    /// The error lists the missing indexes.
+    pub fn try_from_complete(values: BTreeMap<PcrIndex, Pcr>) -> Result<Self, IncompletePcrsError> {
+        let missing = PCR_INDEXES
+            .into_iter()
+            .filter(|index| !values.contains_key(index))
+            .collect::<Vec<PcrIndex>>();
+        if !missing.is_empty() {
+            return Err(IncompletePcrsError::new((), MissingPcrs(missing)));
         }
 
-        pcrs
+        Pcrs::try_from(values)
+    }
+
+    /// Parses PCRs 0 through 15 from hex strings, ordered by index, i.e. fixtures storing each PCR as 96 hex characters.
+    /// Fails if any string isn't a hex encoded [`Pcr`], or they don't all belong to the same [`PcrDigest`] bank. The inverse of [`Pcrs::to_hex_array`].
+    pub fn from_hex_array<S: AsRef<str>>(values: [S; 16]) -> Result<Self, PcrLengthError> {
+        let values = PCR_INDEXES
+            .into_iter()
+            .zip(values)
+            .map(|(index, value)| Ok((index, value.as_ref().parse()?)))
+            .collect::<Result<Vec<(PcrIndex, Pcr)>, PcrLengthError>>()?;
+
+        Pcrs::from_values(values)
+    }
+
+    /// PCRs 0 through 15 as lowercase hex strings, ordered by index. PCRs 16 through 31 are omitted.
+    pub fn to_hex_array(&self) -> [String; 16] {
+        PCR_INDEXES.map(|index| self.get(index).to_string())
+    }
+}
+
+/// Lists the [`PcrIndex`]es missing from [`Pcrs::try_from_complete`]
+#[derive(Debug)]
+struct MissingPcrs(Vec<PcrIndex>);
+
+impl Display for MissingPcrs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PCRs are missing at indexes {:?}", self.0)
+    }
+}
+
+impl std::error::Error for MissingPcrs {}
+
+/// Iterates over the [`Pcr`]s of a [`Pcrs`], see [`Pcrs::iter`]
+pub struct Iter<'a>(std::collections::btree_map::Iter<'a, PcrIndex, Pcr>);
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (PcrIndex, &'a Pcr);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(index, pcr)| (*index, pcr))
+    }
+}
+
+impl<'a> IntoIterator for &'a Pcrs {
+    type Item = (PcrIndex, &'a Pcr);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Consumes [`Pcrs`], yielding the same PCRs as [`Pcrs::iter`]
+impl IntoIterator for Pcrs {
+    type Item = (PcrIndex, Pcr);
+    type IntoIter = std::collections::btree_map::IntoIter<PcrIndex, Pcr>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// Serializes [`Pcrs`] as a map of PCR index to hex string, i.e. `{"0": "0000..."}`.
+/// Indexes are serialized as strings, as formats like TOML only support string keys.
+impl Serialize for Pcrs {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(
+            self.0
+                .iter()
+                .map(|(index, pcr)| (usize::from(*index).to_string(), pcr)),
+        )
+    }
+}
+
+/// Deserializes [`Pcrs`] from a map of PCR index to hex string. Indexes may be strings or integers.
+/// Like [`Pcrs::try_from`], omitted indexes are replaced with all zeros of the provided PCRs' [`PcrDigest`] bank, and PCRs from different banks are rejected.
+impl<'de> Deserialize<'de> for Pcrs {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let values = BTreeMap::<PcrKey, Pcr>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(PcrKey(index), pcr)| {
+                let index = PcrIndex::try_from(index).map_err(|_| {
+                    de::Error::invalid_value(
+                        de::Unexpected::Unsigned(index as u64),
+                        &"a PCR index between 0 and 31",
+                    )
+                })?;
+                Ok((index, pcr))
+            })
+            .collect::<Result<Vec<(PcrIndex, Pcr)>, D::Error>>()?;
+
+        Pcrs::from_values(values).map_err(de::Error::custom)
+    }
+}
+
+/// A PCR index used as a map key, deserialized from either a string or an integer
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct PcrKey(usize);
+
+impl<'de> Deserialize<'de> for PcrKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct PcrKeyVisitor;
+
+        impl de::Visitor<'_> for PcrKeyVisitor {
+            type Value = PcrKey;
+
+            fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+                write!(f, "a PCR index, as a string or an integer")
+            }
+
+            fn visit_u64<E: de::Error>(self, index: u64) -> Result<Self::Value, E> {
+                usize::try_from(index)
+                    .map(PcrKey)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(index), &self))
+            }
+
+            fn visit_str<E: de::Error>(self, index: &str) -> Result<Self::Value, E> {
+                index
+                    .parse()
+                    .map(PcrKey)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Str(index), &self))
+            }
+        }
+
+        deserializer.deserialize_str(PcrKeyVisitor)
     }
 }
 
 /// [`aws_nitro_enclaves_nsm_api::api::AttestationDoc`] stores PCRs as a BTreeMap.
-impl Into<BTreeMap<usize, ByteBuf>> for Pcrs {
-    fn into(self) -> BTreeMap<usize, ByteBuf> {
+impl From<Pcrs> for BTreeMap<usize, ByteBuf> {
+    fn from(pcrs: Pcrs) -> Self {
         let mut map = BTreeMap::new();
-        for (index, value) in self.0.into_iter() {
-            map.insert(index.into(), ByteBuf::from(*value));
+        for (index, value) in pcrs.0.into_iter() {
+            map.insert(index.into(), ByteBuf::from(value.to_vec()));
         }
 
         map
@@ -158,19 +534,23 @@ impl Pcrs {
     /// All PCRs will be zeros
     /// Example: 000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000
     pub fn zeros() -> Self {
-        Pcrs::from_fn(|_| [0; PCR_LENGTH].into())
+        Pcrs::zeros_with_digest(PcrDigest::Sha384)
+    }
+
+    /// All PCRs will be zeros, with the length of the provided [`PcrDigest`].
+    /// Use [`PcrDigest::Sha256`] to mock a SHA256 PCR bank.
+    pub fn zeros_with_digest(digest: PcrDigest) -> Self {
+        Pcrs::from_fn(|_| Pcr::zeros(digest))
     }
 
     #[cfg(feature = "rand")]
-    /// All PCRs will be randomly generated to mimic SHA386 hashes
+    /// All PCRs will be randomly generated to mimic SHA386 hashes. Each byte covers the full 0x00-0xFF range.
     pub fn rand() -> Self {
-        use rand::{distributions::Alphanumeric, Rng};
+        use rand::RngCore;
         Pcrs::from_fn(|_| {
-            let bytes = rand::thread_rng()
-                .sample_iter(&Alphanumeric)
-                .take(PCR_LENGTH)
-                .collect::<Vec<u8>>();
-            Pcr::try_from(bytes).expect("rand must produce valid Pcr")
+            let mut bytes = [0; PCR_LENGTH];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            bytes.into()
         })
     }
 
@@ -178,7 +558,7 @@ impl Pcrs {
     /// All PCRs will be seeded from the provided strings. Each string gets hashed with SHA386.
     /// If a given [`PcrIndex`] is omitted in the BTreeMap, it will be replaced with all zeros.
     pub fn seed(values: BTreeMap<PcrIndex, String>) -> Self {
-        use p384::ecdsa::signature::digest::Digest;
+        use sha2::Digest;
 
         let mut pcrs = Pcrs::zeros();
         for (index, seed) in values {
@@ -189,7 +569,7 @@ impl Pcrs {
                 .to_vec()
                 .try_into()
                 .expect("Pcr should accept any Sha384");
-            pcrs.set(index, pcr);
+            pcrs.0.insert(index, pcr);
         }
 
         pcrs
@@ -198,15 +578,90 @@ impl Pcrs {
 
 /// Getters and setters
 impl Pcrs {
+    /// PCRs 16 through 31 are all zeros until they are set, matching the Nitro Secure Module.
     pub fn get(&self, index: PcrIndex) -> &Pcr {
+        const SHA256_ZEROS: &Pcr = &Pcr(PcrValue::Sha256([0; SHA256_PCR_LENGTH]));
+        const SHA384_ZEROS: &Pcr = &Pcr(PcrValue::Sha384([0; PCR_LENGTH]));
+        // PCRs 0 through 15 are always present, the Pcrs api is designed to ensure this.
+        self.0.get(&index).unwrap_or(match self.digest() {
+            PcrDigest::Sha256 => SHA256_ZEROS,
+            PcrDigest::Sha384 => SHA384_ZEROS,
+        })
+    }
+
+    /// The [`PcrDigest`] bank of these PCRs, determined by PCR 0. Every [`Pcr`] is expected to belong to the same bank.
+    pub fn digest(&self) -> PcrDigest {
         self.0
-            .get(&index)
-            // The Pcrs api is designed to prevent this from happening.
-            .expect("Pcrs were created with invalid indexes")
+            .get(&PcrIndex::Zero)
+            .map(Pcr::digest)
+            .unwrap_or_default()
+    }
+
+    /// Replaces the PCR at `index`. Fails if `pcr` doesn't belong to the [`PcrDigest`] bank of these PCRs, see [`Pcrs::digest`].
+    pub fn set(&mut self, index: PcrIndex, pcr: Pcr) -> Result<(), PcrDigestError> {
+        if pcr.digest() != self.digest() {
+            return Err(PcrDigestError::new(
+                (),
+                ErrorContext("Every PCR must belong to the same PcrDigest bank"),
+            ));
+        }
+
+        self.0.insert(index, pcr);
+        Ok(())
     }
 
-    pub fn set(&mut self, index: PcrIndex, pcr: Pcr) {
+    /// Iterates over PCRs 0 through 15, plus any of 16 through 31 that were set, ordered by [`PcrIndex`].
+    /// These are the PCRs included in the attestation document.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.0.iter())
+    }
+
+    /// Returns the indexes of the PCRs that don't match `expected`, allowing callers to report exactly which PCRs mismatched.
+    /// Each [`Pcr`] is compared in constant time.
+    pub fn diff(&self, expected: &Pcrs) -> Vec<PcrIndex> {
+        ALL_PCR_INDEXES
+            .into_iter()
+            .filter(|index| !self.get(*index).ct_eq(expected.get(*index)))
+            .collect()
+    }
+
+    #[cfg(feature = "extend")]
+    /// Replaces the PCR at `index` with the hash of everything read from `reader`, i.e. a file, EIF, or kernel image.
+    /// The hash algorithm matches the PCR's [`PcrDigest`] bank, which is SHA384 unless [`Pcrs::zeros_with_digest`] was used.
+    /// The bytes are streamed, so large images don't need to be loaded into memory. A `&[u8]` can be provided when there's no file system, i.e. in WebAssembly.
+    pub fn measure<R>(&mut self, index: PcrIndex, mut reader: R) -> std::io::Result<()>
+    where
+        R: std::io::Read,
+    {
+        fn hash<D, R>(reader: &mut R) -> std::io::Result<Vec<u8>>
+        where
+            D: sha2::Digest + std::io::Write,
+            R: std::io::Read,
+        {
+            let mut hasher = D::new();
+            std::io::copy(reader, &mut hasher)?;
+            Ok(hasher.finalize().to_vec())
+        }
+
+        let bytes = match self.get(index).digest() {
+            PcrDigest::Sha256 => hash::<sha2::Sha256, _>(&mut reader)?,
+            PcrDigest::Sha384 => hash::<sha2::Sha384, _>(&mut reader)?,
+        };
+        let pcr = bytes.try_into().expect("Pcr should accept any digest");
         self.0.insert(index, pcr);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "extend")]
+    /// Extends the PCR at `index` with `data`, replacing it with `HASH(current value || data)`, where `HASH` matches the PCR's [`PcrDigest`] bank.
+    /// This mirrors how the Nitro Secure Module builds PCRs, allowing a PCR to be derived from a sequence of measurements.
+    pub fn extend(&mut self, index: PcrIndex, data: &[u8]) {
+        use std::io::Read;
+
+        let current = self.get(index).clone();
+        self.measure(index, current.chain(data))
+            .expect("Reading from slices is infallible");
     }
 }
 
@@ -222,6 +677,46 @@ mod tests {
         assert!(all_zeros);
     }
 
+    #[test]
+    fn pcr_index_all() {
+        assert_eq!(PcrIndex::all().len(), 32);
+        for (index, pcr_index) in PcrIndex::all().iter().enumerate() {
+            assert!(PcrIndex::is_valid(index));
+            assert_eq!(PcrIndex::try_from(index).unwrap(), *pcr_index);
+        }
+        assert!(!PcrIndex::is_valid(32));
+        assert!(PcrIndex::try_from(32).is_err());
+    }
+
+    #[test]
+    fn try_from_complete() {
+        let mut values = PCR_INDEXES
+            .into_iter()
+            .map(|index| (index, Pcr::from([1; PCR_LENGTH])))
+            .collect::<BTreeMap<PcrIndex, Pcr>>();
+        let pcrs = Pcrs::try_from_complete(values.clone()).unwrap();
+        assert_eq!(pcrs.get(PcrIndex::Fifteen), &Pcr::from([1; PCR_LENGTH]));
+
+        values.remove(&PcrIndex::Three);
+        values.remove(&PcrIndex::Eight);
+        let err = Pcrs::try_from_complete(values).unwrap_err();
+        assert!(err.to_string().contains("[Three, Eight]"));
+    }
+
+    #[test]
+    fn hex_array() {
+        let mut pcrs = Pcrs::zeros();
+        pcrs.set(PcrIndex::Two, Pcr::from([2; PCR_LENGTH])).unwrap();
+        let hex = pcrs.to_hex_array();
+        assert_eq!(hex[0], "0".repeat(96));
+        assert_eq!(hex[2], "02".repeat(48));
+        assert_eq!(Pcrs::from_hex_array(hex).unwrap(), pcrs);
+
+        let mut hex = Pcrs::zeros().to_hex_array();
+        hex[5] = "00".to_string();
+        assert!(Pcrs::from_hex_array(hex).is_err());
+    }
+
     #[test]
     fn pcrs_defaults_to_zero() {
         let pcrs = Pcrs::default();
@@ -243,7 +738,132 @@ mod tests {
         Pcr::try_from(too_long).unwrap_err();
 
         let just_right = vec![0; PCR_LENGTH];
-        assert!(Pcr::try_from(just_right).is_ok());
+        assert_eq!(
+            Pcr::try_from(just_right).unwrap().digest(),
+            PcrDigest::Sha384
+        );
+    }
+
+    #[test]
+    fn iter() {
+        let mut pcrs = Pcrs::zeros();
+        pcrs.set(PcrIndex::Three, Pcr::from([3; 48])).unwrap();
+        pcrs.set(PcrIndex::Twenty, Pcr::from([20; 48])).unwrap();
+
+        let indexes = pcrs.iter().map(|(index, _)| index).collect::<Vec<_>>();
+        assert_eq!(indexes.len(), 17);
+        assert_eq!(indexes[3], PcrIndex::Three);
+        assert_eq!(indexes[16], PcrIndex::Twenty);
+
+        for (index, pcr) in &pcrs {
+            assert_eq!(pcr, pcrs.get(index));
+        }
+        assert!(pcrs
+            .clone()
+            .into_iter()
+            .eq(pcrs.iter().map(|(index, pcr)| (index, pcr.clone()))));
+    }
+
+    #[test]
+    fn ct_eq() {
+        let mut last_byte = [1; 48];
+        last_byte[47] = 2;
+        for (a, b) in [
+            (Pcr::from([1; 48]), Pcr::from([1; 48])),
+            (Pcr::from([1; 48]), Pcr::from(last_byte)),
+            (Pcr::from([1; 48]), Pcr::from([1; 32])),
+        ] {
+            assert_eq!(a.ct_eq(&b), a == b);
+        }
+    }
+
+    #[test]
+    fn sha256_pcrs() {
+        let sha256 = Pcr::try_from(vec![0; SHA256_PCR_LENGTH]).unwrap();
+        assert_eq!(sha256.digest(), PcrDigest::Sha256);
+        assert_eq!(sha256.len(), PcrDigest::Sha256.pcr_length());
+        assert_eq!(sha256.to_string().parse::<Pcr>().unwrap(), sha256);
+
+        let pcrs = Pcrs::zeros_with_digest(PcrDigest::Sha256);
+        assert_eq!(pcrs.digest(), PcrDigest::Sha256);
+        // Unset custom PCRs match the bank
+        assert_eq!(pcrs.get(PcrIndex::ThirtyOne), &sha256);
+        // Different banks never match
+        assert!(!sha256.ct_eq(&Pcr::zeros(PcrDigest::Sha384)));
+    }
+
+    #[test]
+    fn partial_sha256_pcrs() {
+        let sha256 = Pcr::from([8; SHA256_PCR_LENGTH]);
+        let values = BTreeMap::from([(PcrIndex::Eight, sha256.clone())]);
+        let mut pcrs = Pcrs::try_from(values).unwrap();
+        assert_eq!(pcrs.digest(), PcrDigest::Sha256);
+        assert_eq!(pcrs.get(PcrIndex::Eight), &sha256);
+        // Omitted PCRs are zeros of the provided bank
+        assert_eq!(pcrs.get(PcrIndex::Zero), &Pcr::zeros(PcrDigest::Sha256));
+        assert_eq!(pcrs, {
+            let mut expected = Pcrs::zeros_with_digest(PcrDigest::Sha256);
+            expected.set(PcrIndex::Eight, sha256.clone()).unwrap();
+            expected
+        });
+
+        pcrs.set(PcrIndex::Eight, Pcr::zeros(PcrDigest::Sha384))
+            .unwrap_err();
+        assert_eq!(pcrs.get(PcrIndex::Eight), &sha256);
+
+        let mixed = BTreeMap::from([
+            (PcrIndex::Zero, Pcr::zeros(PcrDigest::Sha384)),
+            (PcrIndex::Eight, sha256),
+        ]);
+        Pcrs::try_from(mixed).unwrap_err();
+
+        let json = format!(r#"{{"8": "{}"}}"#, "08".repeat(SHA256_PCR_LENGTH));
+        assert_eq!(serde_json::from_str::<Pcrs>(&json).unwrap(), pcrs);
+        let mixed = format!(
+            r#"{{"0": "{}", "8": "{}"}}"#,
+            "00".repeat(PCR_LENGTH),
+            "08".repeat(SHA256_PCR_LENGTH)
+        );
+        serde_json::from_str::<Pcrs>(&mixed).unwrap_err();
+
+        let mut hex = pcrs.to_hex_array();
+        assert_eq!(Pcrs::from_hex_array(hex.clone()).unwrap(), pcrs);
+        hex[15] = "00".repeat(PCR_LENGTH);
+        Pcrs::from_hex_array(hex).unwrap_err();
+    }
+
+    #[test]
+    fn pcr_hex_round_trip() {
+        let hex = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f202122232425262728292a2b2c2d2e2f";
+        let pcr = hex.parse::<Pcr>().unwrap();
+        assert_eq!(pcr[47], 0x2f);
+        assert_eq!(pcr.to_string(), hex);
+
+        let upper = hex.to_uppercase().parse::<Pcr>().unwrap();
+        assert_eq!(upper, pcr);
+    }
+
+    #[test]
+    fn pcr_hex_must_be_valid() {
+        // Too short
+        "00".repeat(PCR_LENGTH - 1).parse::<Pcr>().unwrap_err();
+        // Too long
+        "00".repeat(PCR_LENGTH + 1).parse::<Pcr>().unwrap_err();
+        // Not hex
+        "zz".repeat(PCR_LENGTH).parse::<Pcr>().unwrap_err();
+        // Odd length
+        "0".repeat(PCR_LENGTH * 2 - 1).parse::<Pcr>().unwrap_err();
+    }
+
+    #[test]
+    fn pcrs_diff() {
+        let expected = Pcrs::zeros();
+        assert!(Pcrs::zeros().diff(&expected).is_empty());
+
+        let mut actual = Pcrs::zeros();
+        actual.set(PcrIndex::One, [1; PCR_LENGTH].into()).unwrap();
+        actual.set(PcrIndex::Eight, [8; PCR_LENGTH].into()).unwrap();
+        assert_eq!(actual.diff(&expected), vec![PcrIndex::One, PcrIndex::Eight]);
     }
 
     #[test]
@@ -251,10 +871,91 @@ mod tests {
         let pcrs = Pcrs::zeros();
         let map: BTreeMap<usize, ByteBuf> = pcrs.into();
         for index in PCR_INDEXES {
-            assert!(map.get(&index.into()).is_some());
+            assert!(map.contains_key(&index.into()));
         }
 
-        assert!(map.get(&(8 + 1)).is_none());
+        assert_eq!(map.len(), 16);
+        assert!(!map.contains_key(&16));
+
+        let mut pcrs = Pcrs::zeros();
+        pcrs.set(PcrIndex::Sixteen, [16; PCR_LENGTH].into())
+            .unwrap();
+        let map: BTreeMap<usize, ByteBuf> = pcrs.into();
+        assert_eq!(map.get(&16).unwrap().as_slice(), &[16; PCR_LENGTH]);
+    }
+
+    #[test]
+    fn pcrs_json_round_trip() {
+        let mut pcrs = Pcrs::zeros();
+        pcrs.set(PcrIndex::Zero, [0xab; PCR_LENGTH].into()).unwrap();
+        pcrs.set(PcrIndex::Sixteen, [0x10; PCR_LENGTH].into())
+            .unwrap();
+
+        let json = serde_json::to_value(&pcrs).unwrap();
+        assert_eq!(json["0"], "ab".repeat(PCR_LENGTH));
+        assert_eq!(json["16"], "10".repeat(PCR_LENGTH));
+
+        let decoded: Pcrs = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, pcrs);
+    }
+
+    #[test]
+    fn pcrs_json_rejects_invalid_values() {
+        let partial: Pcrs =
+            serde_json::from_str(&format!(r#"{{"8": "{}"}}"#, "08".repeat(PCR_LENGTH))).unwrap();
+        assert_eq!(partial.get(PcrIndex::Eight), &Pcr::from([8; PCR_LENGTH]));
+        assert_eq!(partial.get(PcrIndex::Zero), &Pcr::from([0; PCR_LENGTH]));
+
+        let too_short = format!(r#"{{"0": "{}"}}"#, "00".repeat(PCR_LENGTH - 1));
+        serde_json::from_str::<Pcrs>(&too_short).unwrap_err();
+
+        let not_hex = format!(r#"{{"0": "{}"}}"#, "zz".repeat(PCR_LENGTH));
+        serde_json::from_str::<Pcrs>(&not_hex).unwrap_err();
+
+        let invalid_index = format!(r#"{{"32": "{}"}}"#, "00".repeat(PCR_LENGTH));
+        serde_json::from_str::<Pcrs>(&invalid_index).unwrap_err();
+    }
+
+    #[test]
+    fn pcrs_map_keys() {
+        use serde::de::value::{Error, MapDeserializer};
+
+        // Formats like TOML only support string keys
+        let json = serde_json::to_value(Pcrs::zeros()).unwrap();
+        assert!(json.as_object().unwrap().contains_key("15"));
+
+        let hex = "08".repeat(PCR_LENGTH);
+        let pcrs = Pcrs::deserialize(MapDeserializer::<_, Error>::new(
+            [("8", hex.as_str())].into_iter(),
+        ))
+        .unwrap();
+        assert_eq!(pcrs.get(PcrIndex::Eight), &Pcr::from([8; PCR_LENGTH]));
+
+        let pcrs = Pcrs::deserialize(MapDeserializer::<_, Error>::new(
+            [(8u64, hex.as_str())].into_iter(),
+        ))
+        .unwrap();
+        assert_eq!(pcrs.get(PcrIndex::Eight), &Pcr::from([8; PCR_LENGTH]));
+
+        Pcrs::deserialize(MapDeserializer::<_, Error>::new(
+            [("eight", hex.as_str())].into_iter(),
+        ))
+        .unwrap_err();
+    }
+
+    #[test]
+    fn pcr_index_range() {
+        for index in 0..32 {
+            let pcr_index = PcrIndex::try_from(index).unwrap();
+            assert_eq!(usize::from(pcr_index), index);
+        }
+        PcrIndex::try_from(32).unwrap_err();
+    }
+
+    #[test]
+    fn custom_pcrs_default_to_zero() {
+        let pcrs = Pcrs::zeros();
+        assert_eq!(pcrs.get(PcrIndex::ThirtyOne), &Pcr::from([0; PCR_LENGTH]));
     }
 
     #[cfg(feature = "rand")]
@@ -263,6 +964,73 @@ mod tests {
         let a = Pcrs::rand();
         let b = Pcrs::rand();
         assert_ne!(a, b);
+
+        // 288 random bytes are all but guaranteed to include at least one that isn't alphanumeric ASCII
+        let non_alphanumeric =
+            a.0.values()
+                .flat_map(|pcr| pcr.iter())
+                .any(|b| !b.is_ascii_alphanumeric());
+        assert!(non_alphanumeric);
+    }
+
+    #[cfg(feature = "extend")]
+    #[test]
+    fn measure() {
+        use sha2::{Digest, Sha384};
+
+        let image = vec![7u8; 1024 * 1024];
+        let mut pcrs = Pcrs::zeros();
+        pcrs.measure(PcrIndex::Zero, image.as_slice()).unwrap();
+
+        let expected: [u8; PCR_LENGTH] = Sha384::digest(&image).into();
+        assert_eq!(pcrs.get(PcrIndex::Zero), &Pcr::from(expected));
+
+        let mut again = Pcrs::zeros();
+        again
+            .measure(PcrIndex::Zero, std::io::Cursor::new(image))
+            .unwrap();
+        assert_eq!(again, pcrs);
+    }
+
+    #[cfg(feature = "extend")]
+    #[test]
+    fn sha256_extend() {
+        use sha2::{Digest, Sha256};
+
+        let mut pcrs = Pcrs::zeros_with_digest(PcrDigest::Sha256);
+        pcrs.extend(PcrIndex::Zero, b"first");
+
+        let mut expected = Sha256::new();
+        expected.update([0; SHA256_PCR_LENGTH]);
+        expected.update(b"first");
+        let expected: [u8; SHA256_PCR_LENGTH] = expected.finalize().into();
+        assert_eq!(pcrs.get(PcrIndex::Zero), &Pcr::from(expected));
+    }
+
+    #[cfg(feature = "extend")]
+    #[test]
+    fn extend_is_order_sensitive() {
+        use sha2::{Digest, Sha384};
+
+        let mut a = Pcrs::zeros();
+        a.extend(PcrIndex::Zero, b"first");
+        a.extend(PcrIndex::Zero, b"second");
+
+        let mut b = Pcrs::zeros();
+        b.extend(PcrIndex::Zero, b"second");
+        b.extend(PcrIndex::Zero, b"first");
+
+        assert_ne!(a.get(PcrIndex::Zero), b.get(PcrIndex::Zero));
+        // Only the extended PCR changes
+        assert_eq!(a.get(PcrIndex::One), Pcrs::zeros().get(PcrIndex::One));
+
+        let mut expected = Sha384::new();
+        expected.update([0; PCR_LENGTH]);
+        expected.update(b"first");
+        let expected: [u8; PCR_LENGTH] = expected.finalize().into();
+        let mut c = Pcrs::zeros();
+        c.extend(PcrIndex::Zero, b"first");
+        assert_eq!(c.get(PcrIndex::Zero), &Pcr::from(expected));
     }
 
     #[cfg(feature = "seed")]
@@ -270,7 +1038,7 @@ mod tests {
     fn seed_is_deterministic() {
         let mut seed = BTreeMap::new();
         for index in PCR_INDEXES {
-            seed.insert(index.into(), usize::from(index).to_string());
+            seed.insert(index, usize::from(index).to_string());
         }
         let a = Pcrs::seed(seed.clone());
         let b = Pcrs::seed(seed);
@@ -278,7 +1046,7 @@ mod tests {
 
         let mut alt_seed = BTreeMap::new();
         for index in PCR_INDEXES {
-            alt_seed.insert(index.into(), (usize::from(index) + 1).to_string());
+            alt_seed.insert(index, (usize::from(index) + 1).to_string());
         }
         let c = Pcrs::seed(alt_seed);
         assert_ne!(a, c);
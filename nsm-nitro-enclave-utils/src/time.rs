@@ -1,45 +1,79 @@
-use std::ops::Deref;
 use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::SystemTime;
 
 /// Must return UTC time when document was created expressed as milliseconds since Unix Epoch
 /// This is an `Fn` to support WebAssembly targets, which don't support `SystemTime`
 pub struct Time(Box<dyn Fn() -> u64 + Send + Sync>);
 
-impl Deref for Time {
-    type Target = Duration;
-
-    fn deref(&self) -> &Self::Target {
-        todo!()
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for Time {
+    fn default() -> Self {
+        Self::system_time()
     }
 }
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(target_arch = "wasm32", feature = "js-date"))]
 impl Default for Time {
     fn default() -> Self {
-        Self::system_time()
+        Self::js_date()
     }
 }
 
 impl Time {
     /// Must return UTC time expressed as milliseconds since Unix Epoch.
-    /// If you aren't targeting WebAssembly, you should probably use [`Time::system_time`]
+    /// If you aren't targeting WebAssembly, you should probably use [`Time::system_time`]. Otherwise, consider `Time::js_date`, behind the `js-date` feature.
     pub fn new(getter: Box<dyn Fn() -> u64 + Send + Sync>) -> Self {
         Self(getter)
     }
 
+    /// Creates a new [`Time`] that always returns `millis`, expressed as milliseconds since Unix Epoch.
+    /// Useful for deterministic tests and fixtures.
+    pub fn fixed(millis: u64) -> Self {
+        Self(Box::new(move || millis))
+    }
+
+    /// Creates a new [`Time`] that returns `start_millis` on the first call, advancing by `step_millis` on each following call.
+    /// Useful for testing sequences of attestation documents, i.e. each document being one second later than the last.
+    /// The time saturates at [`u64::MAX`] instead of overflowing.
+    pub fn incrementing(start_millis: u64, step_millis: u64) -> Self {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        let calls = AtomicU64::new(0);
+        Self(Box::new(move || {
+            let calls = calls.fetch_add(1, Ordering::Relaxed);
+            start_millis.saturating_add(calls.saturating_mul(step_millis))
+        }))
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     /// Creates a new [`Time`] using [`std::time::SystemTime`]. Not compatible with WebAssembly targets.
     pub fn system_time() -> Self {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        Self(Box::new(move || {
-            u64::try_from(
-                SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .expect("Land before time 🦕")
-                    .as_millis(),
-            )
-            .expect("This code has exceeded my lifetime")
-        }))
+        Self(Box::new(|| unix_millis(SystemTime::now())))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Creates a new [`Time`] that always returns `system_time`, converted to milliseconds since Unix Epoch like every other [`Time`].
+    /// Sub-millisecond precision is truncated. Not compatible with WebAssembly targets.
+    ///
+    /// Panics if `system_time` is before Unix Epoch.
+    pub fn at(system_time: SystemTime) -> Self {
+        Self::fixed(unix_millis(system_time))
+    }
+
+    #[cfg(all(target_arch = "wasm32", feature = "js-date"))]
+    /// Creates a new [`Time`] using JavaScript's `Date.now()`. Only available on WebAssembly targets, with the `js-date` feature enabled.
+    pub fn js_date() -> Self {
+        Self(Box::new(|| js_sys::Date::now() as u64))
+    }
+
+    #[cfg(feature = "chrono")]
+    /// Creates a new [`Time`] that always returns `datetime`, converted to milliseconds since Unix Epoch like every other [`Time`].
+    /// Sub-millisecond precision is truncated. Only available with the `chrono` feature enabled.
+    ///
+    /// Panics if `datetime` is before Unix Epoch.
+    pub fn from_datetime(datetime: chrono::DateTime<chrono::Utc>) -> Self {
+        let millis = u64::try_from(datetime.timestamp_millis()).expect("Land before time 🦕");
+        Self::fixed(millis)
     }
 
     /// Returns value from inner `getter`
@@ -48,4 +82,92 @@ impl Time {
     pub fn time(&self) -> u64 {
         self.0()
     }
+
+    /// Returns value from inner `getter` as a [`Duration`] since Unix Epoch.
+    /// [`Time`] doesn't implement `Deref<Target = Duration>`, as the value is computed on every call and can't be borrowed.
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.time())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<SystemTime> for Time {
+    /// See [`Time::at`]
+    fn from(system_time: SystemTime) -> Self {
+        Self::at(system_time)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn unix_millis(system_time: SystemTime) -> u64 {
+    u64::try_from(
+        system_time
+            .duration_since(std::time::UNIX_EPOCH)
+            .expect("Land before time 🦕")
+            .as_millis(),
+    )
+    .expect("This code has exceeded my lifetime")
+}
+
+#[cfg(test)]
+mod test {
+    use super::Time;
+    use std::time::Duration;
+
+    #[test]
+    fn duration() {
+        let time = Time::fixed(1_700_000_000_123);
+        assert_eq!(time.duration(), Duration::from_millis(1_700_000_000_123));
+        assert_eq!(time.duration().as_millis() as u64, time.time());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn from_datetime() {
+        use chrono::{TimeZone, Utc};
+
+        let datetime = Utc.timestamp_millis_opt(1_700_000_000_123).unwrap();
+        let time = Time::from_datetime(datetime);
+        assert_eq!(time.time(), 1_700_000_000_123);
+        assert_eq!(time.duration(), Duration::from_millis(1_700_000_000_123));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn at() {
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        let system_time = UNIX_EPOCH + Duration::from_micros(1_700_000_000_123_456);
+        assert_eq!(Time::at(system_time).time(), 1_700_000_000_123);
+        assert_eq!(Time::from(system_time).time(), 1_700_000_000_123);
+
+        let now = SystemTime::now();
+        let millis = now.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        assert_eq!(Time::from(now).time(), millis);
+    }
+
+    #[test]
+    fn fixed() {
+        let time = Time::fixed(1234);
+        assert_eq!(time.time(), 1234);
+        assert_eq!(time.time(), 1234);
+    }
+
+    #[test]
+    fn incrementing() {
+        let time = Time::incrementing(1000, 500);
+        assert_eq!(time.time(), 1000);
+        assert_eq!(time.time(), 1500);
+        assert_eq!(time.time(), 2000);
+
+        let time = Time::incrementing(u64::MAX - 1, 1);
+        assert_eq!(time.time(), u64::MAX - 1);
+        assert_eq!(time.time(), u64::MAX);
+        assert_eq!(time.time(), u64::MAX);
+
+        let time = Time::incrementing(0, u64::MAX);
+        assert_eq!(time.time(), 0);
+        assert_eq!(time.time(), u64::MAX);
+        assert_eq!(time.time(), u64::MAX);
+    }
 }
@@ -0,0 +1,69 @@
+//! Embeds AWS's Nitro Enclaves root certificate, behind the `aws-root` feature.
+//!
+//! #### Provenance
+//! The certificate is the "AWS_NitroEnclaves_Root-G1" root, distributed in the zip linked from AWS's ["verify root" documentation](https://docs.aws.amazon.com/enclaves/latest/user/verify-root.html#validation-process),
+//! converted from PEM to DER. Its SHA-256 fingerprint is [`AWS_ROOT_SHA256_FINGERPRINT`], matching the fingerprint published alongside it.
+//!
+//! #### Update policy
+//! The root is valid until 2049. It's only replaced when AWS publishes a new root certificate, after checking the new fingerprint against AWS's documentation.
+//! A replacement is released as a breaking change, as it changes which attestation documents verify.
+
+use super::AttestationVerifier;
+
+/// The hex encoded SHA-256 fingerprint of [`aws_root_der`], as published by AWS
+pub const AWS_ROOT_SHA256_FINGERPRINT: &str =
+    "641a0321a3e244efe456463195d606317ed7cdcc3c1756e09893f3c68f79bb5b";
+
+/// AWS's Nitro Enclaves root certificate, DER encoded. Authentic attestation documents verify against it.
+pub fn aws_root_der() -> &'static [u8] {
+    include_bytes!("../../certs/aws-nitro-enclaves-root-g1.der")
+}
+
+impl AttestationVerifier {
+    /// Creates a verifier for authentic attestation documents, trusting only [`aws_root_der`].
+    pub fn with_aws_root() -> Self {
+        Self::new(aws_root_der().to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{aws_root_der, AWS_ROOT_SHA256_FINGERPRINT};
+    use sha2::{Digest, Sha256};
+    use webpki::{anchor_from_trusted_cert, types::CertificateDer};
+    use x509_cert::{der::Decode, Certificate};
+
+    #[test]
+    fn aws_root() {
+        let fingerprint = Sha256::digest(aws_root_der());
+        assert_eq!(hex::encode(fingerprint), AWS_ROOT_SHA256_FINGERPRINT);
+
+        anchor_from_trusted_cert(&CertificateDer::from(aws_root_der())).unwrap();
+
+        let cert = Certificate::from_der(aws_root_der()).unwrap();
+        assert_eq!(
+            cert.tbs_certificate.subject.to_string(),
+            "CN=aws.nitro-enclaves,OU=AWS,O=Amazon,C=US"
+        );
+    }
+
+    #[cfg(all(not(target_arch = "wasm32"), feature = "pki"))]
+    #[test]
+    fn with_aws_root() {
+        use crate::verify::{AttestationVerifier, ErrorKind};
+        use std::time::Duration;
+
+        // Self signed attestation documents don't verify against AWS's root
+        let document =
+            nsm_nitro_enclave_utils_keygen::NsmCertChain::generate(Duration::from_secs(60))
+                .dev_nitro_builder()
+                .unwrap()
+                .build()
+                .attest(None, None, None)
+                .unwrap();
+        let err = AttestationVerifier::with_aws_root()
+            .verify(&document)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::UnknownIssuer);
+    }
+}
@@ -1,53 +1,360 @@
-use crate::time::Time;
+use std::borrow::Cow;
 use std::time::Duration;
 use webpki::{
     anchor_from_trusted_cert,
     types::{CertificateDer, TrustAnchor, UnixTime},
-    EndEntityCert, KeyUsage,
+    BorrowedCertRevocationList, CertRevocationList, EndEntityCert, KeyUsage, RevocationCheckDepth,
+    RevocationOptionsBuilder, UnknownStatusPolicy,
 };
 
+use x509_cert::{der::Decode, Certificate};
+
 use super::{ErrorKind, VerifyError};
+use crate::time::Time;
+
+/// Parses a DER encoded root certificate, reporting PEM encoded ones explicitly as webpki's error doesn't distinguish them from malformed certificates
+fn trust_anchor<'a>(root_cert: &'a CertificateDer<'a>) -> Result<TrustAnchor<'a>, VerifyError> {
+    if root_cert.trim_ascii_start().starts_with(b"-----BEGIN") {
+        return Err(VerifyError::new(
+            ErrorKind::RootCertificate,
+            crate::ErrorContext("Expected a DER encoded root certificate, got PEM"),
+        ));
+    }
+
+    anchor_from_trusted_cert(root_cert)
+        .map_err(|err| VerifyError::new(ErrorKind::RootCertificate, err))
+}
+
+/// Parses every DER encoded root certificate into a [`TrustAnchor`] that owns its data, so it can be parsed once and reused.
+/// Fails with [`ErrorKind::RootCertificate`] if none are provided, or any of them can't be parsed.
+pub(crate) fn owned_trust_anchors(
+    root_certs: &[Vec<u8>],
+) -> Result<Vec<TrustAnchor<'static>>, VerifyError> {
+    if root_certs.is_empty() {
+        return Err(VerifyError::new(
+            ErrorKind::RootCertificate,
+            crate::ErrorContext("At least one root certificate must be provided"),
+        ));
+    }
+
+    root_certs
+        .iter()
+        .map(|root_cert| {
+            trust_anchor(&CertificateDer::from(root_cert.as_slice()))
+                .map(|anchor| anchor.to_owned())
+        })
+        .collect()
+}
+
+/// Verifies that the DER encoded `leaf` certificate chains up to `root` through `intermediates` at `time`, without an attestation document.
+/// Applies the same checks as [`AttestationVerifier`](super::AttestationVerifier) with its defaults, i.e. no CRLs, no clock skew, and [`KeyUsage::server_auth`].
+/// Useful as a pre-flight check for a certificate chain before it's used to sign attestation documents.
+pub fn verify_cert_chain(
+    root: &[u8],
+    intermediates: &[Vec<u8>],
+    leaf: &[u8],
+    time: Time,
+) -> Result<(), VerifyError> {
+    let root_certs = [CertificateDer::from(root)];
+    let int_certs = intermediates
+        .iter()
+        .map(|bytes| CertificateDer::from(bytes.as_slice()))
+        .collect::<Vec<CertificateDer>>();
+    let end_cert = CertificateDer::from(leaf);
+
+    ChainVerifier::new(&root_certs, int_certs, &end_cert)?
+        .verify(time.duration(), Duration::ZERO)
+        .map(|_| ())
+}
 
 #[must_use = "ChainVerifier must be verified"]
 pub(crate) struct ChainVerifier<'a> {
-    root_cert: TrustAnchor<'a>,
+    root_certs: Cow<'a, [TrustAnchor<'a>]>,
     int_certs: Vec<CertificateDer<'a>>,
     end_cert: EndEntityCert<'a>,
+    crls: Vec<CertRevocationList<'a>>,
+    key_usage: KeyUsage,
 }
 
 impl<'a> ChainVerifier<'a> {
+    /// The chain is trusted if it verifies against any of `root_certs`
     pub(crate) fn new(
-        root_cert: &'a CertificateDer,
+        root_certs: &'a [CertificateDer],
         int_certs: Vec<CertificateDer<'a>>,
         end_cert: &'a CertificateDer,
     ) -> Result<Self, VerifyError> {
         let end_cert = EndEntityCert::try_from(end_cert)
             .map_err(|err| VerifyError::new(ErrorKind::EndCertificate, err))?;
-        let root_cert = anchor_from_trusted_cert(root_cert)
-            .map_err(|err| VerifyError::new(ErrorKind::RootCertificate, err))?;
+        if root_certs.is_empty() {
+            return Err(VerifyError::new(
+                ErrorKind::RootCertificate,
+                crate::ErrorContext("At least one root certificate must be provided"),
+            ));
+        }
+        let root_certs = root_certs
+            .iter()
+            .map(trust_anchor)
+            .collect::<Result<Vec<TrustAnchor>, VerifyError>>()?;
 
         Ok(Self {
-            root_cert,
+            root_certs: Cow::Owned(root_certs),
             int_certs,
             end_cert,
+            crls: Vec::new(),
+            key_usage: KeyUsage::server_auth(),
         })
     }
 
-    /// Verifies the certificate chain
-    /// AWS's documentation explicitly requires ["CRL must be disabled when doing the validation"](https://docs.aws.amazon.com/enclaves/latest/user/verify-root.html#chain)
-    pub(crate) fn verify(self, get_time: Time) -> Result<(), VerifyError> {
-        self.end_cert
+    /// Behaves like [`ChainVerifier::new`], trusting root certificates that were already parsed, see [`owned_trust_anchors`]
+    pub(crate) fn with_anchors(
+        root_certs: &'a [TrustAnchor<'a>],
+        int_certs: Vec<CertificateDer<'a>>,
+        end_cert: &'a CertificateDer,
+    ) -> Result<Self, VerifyError> {
+        let end_cert = EndEntityCert::try_from(end_cert)
+            .map_err(|err| VerifyError::new(ErrorKind::EndCertificate, err))?;
+
+        Ok(Self {
+            root_certs: Cow::Borrowed(root_certs),
+            int_certs,
+            end_cert,
+            crls: Vec::new(),
+            key_usage: KeyUsage::server_auth(),
+        })
+    }
+
+    /// Check the revocation status of every certificate in the chain against the DER encoded `crls`.
+    /// A certificate that isn't covered by any of the CRLs is treated as not revoked.
+    pub(crate) fn with_crls(self, crls: &'a [Vec<u8>]) -> Result<Self, VerifyError> {
+        let crls = crls
+            .iter()
+            .map(|crl| BorrowedCertRevocationList::from_der(crl).map(CertRevocationList::from))
+            .collect::<Result<Vec<CertRevocationList>, webpki::Error>>()
+            .map_err(|err| VerifyError::new(ErrorKind::Crl, err))?;
+
+        Ok(Self { crls, ..self })
+    }
+
+    /// Set the extended key usage the end certificate must assert. Defaults to [`KeyUsage::server_auth`].
+    pub(crate) fn with_key_usage(self, key_usage: KeyUsage) -> Self {
+        Self { key_usage, ..self }
+    }
+
+    /// Verifies the certificate chain at `now`, expressed as the duration since Unix Epoch.
+    /// If the chain is expired, or not yet valid, at `now` it is verified again at `now` shifted by `allow_skew` in the direction that would make it valid.
+    /// AWS's documentation explicitly requires ["CRL must be disabled when doing the validation"](https://docs.aws.amazon.com/enclaves/latest/user/verify-root.html#chain),
+    /// so revocation is only checked when CRLs were provided via [`ChainVerifier::with_crls`].
+    ///
+    /// Returns the period during which every certificate of the verified path, from the end certificate to the intermediate issued by the root certificate, is valid.
+    /// Intermediates that weren't part of the path aren't parsed.
+    pub(crate) fn verify(
+        &self,
+        now: Duration,
+        allow_skew: Duration,
+    ) -> Result<Validity, VerifyError> {
+        let path = verify_chain(
+            &self.root_certs,
+            &self.int_certs,
+            &self.end_cert,
+            &self.crls,
+            self.key_usage,
+            now,
+            allow_skew,
+        )?;
+
+        chain_validity(path.iter().map(Vec::as_slice))
+    }
+}
+
+/// Behaves like [`verify_cert_chain`], parsing the root certificates and CRLs once so they can be reused to verify many certificate chains.
+/// Owns everything it parsed, so it can be stored in a long-lived struct or shared between threads.
+///
+/// ```rust,no_run
+/// use nsm_nitro_enclave_utils::{time::Time, verify::OwnedChainVerifier};
+///
+/// let root_cert_der = include_bytes!("../../../test_data/root-certificate.der").to_vec();
+/// let intermediates = vec![include_bytes!("../../../test_data/int-certificate.der").to_vec()];
+/// let leaf = include_bytes!("../../../test_data/end-certificate.der");
+///
+/// let verifier = OwnedChainVerifier::new(vec![root_cert_der]).unwrap();
+/// verifier.verify(&intermediates, leaf, Time::default()).unwrap();
+/// ```
+pub struct OwnedChainVerifier {
+    root_certs: Vec<TrustAnchor<'static>>,
+    crls: Vec<CertRevocationList<'static>>,
+    key_usage: KeyUsage,
+    allow_skew: Duration,
+}
+
+impl OwnedChainVerifier {
+    /// `root_certs`: DER encoded root certificates, a chain must verify against at least one of them.
+    /// Fails with [`ErrorKind::RootCertificate`] if none are provided, or any of them can't be parsed.
+    pub fn new(root_certs: Vec<Vec<u8>>) -> Result<Self, VerifyError> {
+        Ok(Self {
+            root_certs: owned_trust_anchors(&root_certs)?,
+            crls: Vec::new(),
+            key_usage: KeyUsage::server_auth(),
+            allow_skew: Duration::ZERO,
+        })
+    }
+
+    /// Check the revocation status of every certificate in the chain against the DER encoded `crls`. Defaults to none, disabling revocation checks.
+    /// Fails with [`ErrorKind::Crl`] if any of them can't be parsed.
+    pub fn crls(self, crls: &[Vec<u8>]) -> Result<Self, VerifyError> {
+        let crls = crls
+            .iter()
+            .map(|crl| {
+                BorrowedCertRevocationList::from_der(crl)
+                    .and_then(|crl| crl.to_owned())
+                    .map(CertRevocationList::from)
+            })
+            .collect::<Result<Vec<CertRevocationList>, webpki::Error>>()
+            .map_err(|err| VerifyError::new(ErrorKind::Crl, err))?;
+
+        Ok(Self { crls, ..self })
+    }
+
+    /// Set the extended key usage the end certificate must assert. Defaults to [`KeyUsage::server_auth`].
+    pub fn key_usage(self, key_usage: KeyUsage) -> Self {
+        Self { key_usage, ..self }
+    }
+
+    /// Accept a certificate chain that is expired, or not yet valid, by up to `allow_skew`. Defaults to zero.
+    pub fn allow_skew(self, allow_skew: Duration) -> Self {
+        Self { allow_skew, ..self }
+    }
+
+    /// Verifies that the DER encoded `leaf` certificate chains up to one of the root certificates through `intermediates` at `time`.
+    pub fn verify(
+        &self,
+        intermediates: &[Vec<u8>],
+        leaf: &[u8],
+        time: Time,
+    ) -> Result<(), VerifyError> {
+        let int_certs = intermediates
+            .iter()
+            .map(|bytes| CertificateDer::from(bytes.as_slice()))
+            .collect::<Vec<CertificateDer>>();
+        let end_cert = CertificateDer::from(leaf);
+        let end_cert = EndEntityCert::try_from(&end_cert)
+            .map_err(|err| VerifyError::new(ErrorKind::EndCertificate, err))?;
+
+        verify_chain(
+            &self.root_certs,
+            &int_certs,
+            &end_cert,
+            &self.crls,
+            self.key_usage,
+            time.duration(),
+            self.allow_skew,
+        )
+        .map(|_| ())
+    }
+}
+
+/// Verifies `end_cert` at `now`, retrying at `now` shifted by `allow_skew` if the chain is expired or not yet valid, see [`ChainVerifier::verify`].
+/// Returns the DER encoded certificates of the verified path, its intermediates followed by `end_cert`.
+fn verify_chain(
+    root_certs: &[TrustAnchor],
+    int_certs: &[CertificateDer],
+    end_cert: &EndEntityCert,
+    crls: &[CertRevocationList],
+    key_usage: KeyUsage,
+    now: Duration,
+    allow_skew: Duration,
+) -> Result<Vec<Vec<u8>>, VerifyError> {
+    let verify_at = |now: Duration| {
+        let crls = crls.iter().collect::<Vec<&CertRevocationList>>();
+        let revocation = RevocationOptionsBuilder::new(&crls).ok().map(|builder| {
+            builder
+                .with_depth(RevocationCheckDepth::Chain)
+                .with_status_policy(UnknownStatusPolicy::Allow)
+                .build()
+        });
+
+        end_cert
             .verify_for_usage(
                 &[webpki::ring::ECDSA_P384_SHA384],
-                &[self.root_cert],
-                &self.int_certs,
-                UnixTime::since_unix_epoch(Duration::from_millis(get_time.time())),
-                KeyUsage::server_auth(),
-                None,
+                root_certs,
+                int_certs,
+                UnixTime::since_unix_epoch(now),
+                key_usage,
+                revocation,
                 None,
             )
-            .map_err(|err| VerifyError::new(ErrorKind::Verification, err))?;
+            .map(|path| {
+                path.intermediate_certificates()
+                    .map(|cert| cert.der().to_vec())
+                    .chain([end_cert.der().to_vec()])
+                    .collect::<Vec<Vec<u8>>>()
+            })
+    };
+
+    let skewed = match verify_at(now) {
+        Err(webpki::Error::CertExpired) if !allow_skew.is_zero() => {
+            verify_at(now.saturating_sub(allow_skew))
+        }
+        Err(webpki::Error::CertNotValidYet) if !allow_skew.is_zero() => {
+            verify_at(now.saturating_add(allow_skew))
+        }
+        result => result,
+    };
 
-        Ok(())
+    skewed.map_err(|err| {
+        let kind = match err {
+            webpki::Error::CertExpired => ErrorKind::CertificateExpired,
+            webpki::Error::CertNotValidYet => ErrorKind::CertificateNotYetValid,
+            webpki::Error::UnknownIssuer => ErrorKind::UnknownIssuer,
+            webpki::Error::InvalidSignatureForPublicKey => ErrorKind::BadSignature,
+            webpki::Error::UnsupportedSignatureAlgorithm
+            | webpki::Error::UnsupportedSignatureAlgorithmForPublicKey => {
+                ErrorKind::UnsupportedSignatureAlgorithm
+            }
+            webpki::Error::CertRevoked => ErrorKind::CertificateRevoked,
+            webpki::Error::RequiredEkuNotFound => ErrorKind::KeyUsage,
+            webpki::Error::InvalidCrlSignatureForPublicKey
+            | webpki::Error::IssuerNotCrlSigner
+            | webpki::Error::UnsupportedCrlSignatureAlgorithm
+            | webpki::Error::UnsupportedCrlSignatureAlgorithmForPublicKey => ErrorKind::Crl,
+            _ => ErrorKind::Verification,
+        };
+        VerifyError::new(kind, err)
+    })
+}
+
+/// The period during which a certificate is valid, expressed as durations since Unix Epoch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Validity {
+    pub not_before: Duration,
+    pub not_after: Duration,
+}
+
+impl Validity {
+    pub(crate) fn of(cert: &Certificate) -> Self {
+        let validity = &cert.tbs_certificate.validity;
+        Self {
+            not_before: validity.not_before.to_unix_duration(),
+            not_after: validity.not_after.to_unix_duration(),
+        }
     }
 }
+
+/// The period during which every DER encoded certificate in `certs` is valid: the latest `not_before` and the earliest `not_after`.
+/// If their validity periods don't overlap, `not_before` is after `not_after`.
+pub(crate) fn chain_validity<'b>(
+    certs: impl IntoIterator<Item = &'b [u8]>,
+) -> Result<Validity, VerifyError> {
+    let mut chain = Validity {
+        not_before: Duration::ZERO,
+        not_after: Duration::MAX,
+    };
+    for cert in certs {
+        let cert = Certificate::from_der(cert)
+            .map_err(|err| VerifyError::new(ErrorKind::AttestationDoc, err))?;
+        let validity = Validity::of(&cert);
+        chain.not_before = chain.not_before.max(validity.not_before);
+        chain.not_after = chain.not_after.min(validity.not_after);
+    }
+
+    Ok(chain)
+}
@@ -1,19 +1,27 @@
 //! Implements a verifier extension trait for [AWS Nitro Enclave attestation documents](https://aws.amazon.com/blogs/compute/validating-attestation-documents-produced-by-aws-nitro-enclaves/).
 //! [`AttestationDocVerifierExt`] is designed to be work with both authentic AWS-signed attestation documents and ones signed with your own PKI, with the goal of enabling a seamless transition between testing in a local development environment and remote enclave environments.
 //! A client that utilizes [`AttestationDocVerifierExt`] must be made aware of the root certificate it expects to verify against.
-//! When a client is expecting an authentic AWS-signed attestation document, [`AttestationDocVerifierExt`] should be provided AWS's root certificate, which can be downloaded [from their documentation](https://docs.aws.amazon.com/enclaves/latest/user/verify-root.html#validation-process).
+//! When a client is expecting an authentic AWS-signed attestation document, [`AttestationDocVerifierExt`] should be provided AWS's root certificate, which can be downloaded [from their documentation](https://docs.aws.amazon.com/enclaves/latest/user/verify-root.html#validation-process),
+//! or, with the `aws-root` feature enabled, read from [`aws_root_der`] and [`AttestationVerifier::with_aws_root`].
 //! When a client is expecting a self-signed attestation document via `nsm-nitro-enclave-utils`'s "bring your own pki" support, [`AttestationDocVerifierExt`] should be provided your root certificate, which can be generated with `nsm-nitro-enclave-utils-keygen`.
 
-use coset::{CborSerializable, CoseSign1};
-use p384::ecdsa::{signature::Verifier, Signature, VerifyingKey};
 use sealed::sealed;
-use webpki::types::CertificateDer;
-use x509_cert::{der::Decode, Certificate};
+use std::collections::BTreeMap;
+use x509_cert::{der::Encode, Certificate};
 
+#[cfg(feature = "aws-root")]
+mod aws;
 mod cert;
+mod verifier;
 use crate::api::nsm::AttestationDoc;
+pub use crate::api::MAX_DOCUMENT_SIZE;
+use crate::pcr::{Pcr, PcrIndex};
 use crate::time::Time;
-use cert::ChainVerifier;
+#[cfg(feature = "aws-root")]
+pub use aws::{aws_root_der, AWS_ROOT_SHA256_FINGERPRINT};
+pub use cert::{verify_cert_chain, OwnedChainVerifier, Validity};
+pub use verifier::{AttestationVerifier, VerifiedAttestationDoc};
+pub use webpki::KeyUsage;
 
 pub type VerifyError = crate::Error<ErrorKind>;
 
@@ -24,6 +32,36 @@ pub enum ErrorKind {
     Verification,
     EndCertificate,
     RootCertificate,
+    Nonce,
+    /// The attestation document's `user_data` is missing or doesn't match, see [`AttestationVerifier::user_data`]
+    UserData,
+    Pcr,
+    /// A certificate in the chain expired before the verification time
+    CertificateExpired,
+    /// A certificate in the chain isn't valid until after the verification time
+    CertificateNotYetValid,
+    /// The chain doesn't lead to any of the trusted root certificates
+    UnknownIssuer,
+    /// A certificate's signature doesn't match its issuer's public key
+    BadSignature,
+    /// A certificate is signed with an algorithm other than ECDSA P-384 with SHA-384, the only algorithm AWS uses
+    UnsupportedSignatureAlgorithm,
+    /// A certificate in the chain has been revoked by one of the provided CRLs
+    CertificateRevoked,
+    /// A provided CRL couldn't be decoded, or wasn't signed by the issuer it claims
+    Crl,
+    /// The end certificate doesn't assert the required extended key usage, see [`AttestationVerifier::key_usage`]
+    KeyUsage,
+    /// The attestation document was produced by an enclave running in debug mode, see [`AttestationVerifier::deny_debug_mode`]
+    DebugMode,
+    /// The attestation document's `timestamp` is outside its certificate's validity period, see [`AttestationVerifier::timestamp_within_validity`]
+    Timestamp,
+    /// The attestation document's `module_id` doesn't match, see [`AttestationVerifier::module_id`]
+    ModuleId,
+    /// The attestation document is larger than allowed, see [`AttestationVerifier::max_document_size`]
+    DocumentSize,
+    /// The attestation document's `digest` doesn't match the hash of the COSE signing algorithm, i.e. a SHA256 digest signed with ES384
+    Digest,
 }
 
 #[sealed]
@@ -33,6 +71,32 @@ pub trait AttestationDocVerifierExt {
         root_cert: &[u8],
         time: Time,
     ) -> Result<AttestationDoc, VerifyError>;
+
+    /// Behaves like [`AttestationDocVerifierExt::from_cose`], accepting an already parsed root certificate, i.e. one generated with `nsm-nitro-enclave-utils-keygen`.
+    /// Fails with [`ErrorKind::RootCertificate`] if `root_cert` can't be DER encoded.
+    fn from_cose_with_root(
+        cose_attestation_doc: &[u8],
+        root_cert: &Certificate,
+        time: Time,
+    ) -> Result<AttestationDoc, VerifyError>;
+
+    /// Behaves like [`AttestationDocVerifierExt::from_cose`], additionally requiring the attestation document's nonce to match `expected_nonce`.
+    /// Fails with [`ErrorKind::Nonce`] if the nonce is missing or doesn't match.
+    fn from_cose_with_nonce(
+        cose_attestation_doc: &[u8],
+        root_cert: &[u8],
+        time: Time,
+        expected_nonce: &[u8],
+    ) -> Result<AttestationDoc, VerifyError>;
+
+    /// Behaves like [`AttestationDocVerifierExt::from_cose`], additionally requiring each PCR in `expected_pcrs` to match the attestation document's PCR at the same index.
+    /// Indexes missing from `expected_pcrs` are not checked. Fails with [`ErrorKind::Pcr`] if any pinned PCR differs, listing the mismatched indexes.
+    fn from_cose_with_pcrs(
+        cose_attestation_doc: &[u8],
+        root_cert: &[u8],
+        time: Time,
+        expected_pcrs: &BTreeMap<PcrIndex, Pcr>,
+    ) -> Result<AttestationDoc, VerifyError>;
 }
 
 /// [`AttestationDocVerifierExt`] is designed to be work with both authentic AWS-signed attestation documents and ones signed with your own PKI, with the goal of enabling a seamless transition between testing in a local development environment and remote enclave environments.
@@ -40,12 +104,8 @@ pub trait AttestationDocVerifierExt {
 /// When a client is expecting an authentic AWS-signed attestation document, [`AttestationDocVerifierExt`] should be provided AWS's root certificate, which can be downloaded [from their documentation](https://docs.aws.amazon.com/enclaves/latest/user/verify-root.html#validation-process).
 /// When a client is expecting a self-signed attestation document via `nsm-nitro-enclave-utils`' "bring your own pki" support, [`AttestationDocVerifierExt`] should be provided your root certificate, which can be generated with `nsm-nitro-enclave-utils-keygen`.
 ///
-/// #### Verification process
-/// This implements the 4 steps outlined in the AWS Nitro Enclaves ["verify root" documentation](https://docs.aws.amazon.com/enclaves/latest/user/verify-root.html)
-/// 1. Decode the CBOR object and map it to a COSE_Sign1 structure.
-/// 2. Extract the attestation document from the COSE_Sign1 structure.
-/// 3. Verify the certificate chain.
-/// 4. Ensure that the attestation document is properly signed.
+/// Each method delegates to [`AttestationVerifier`], see its documentation for the verification process.
+/// Prefer [`AttestationVerifier`] when you need to combine checks, i.e. a nonce and expected PCRs.
 #[sealed]
 impl AttestationDocVerifierExt for AttestationDoc {
     fn from_cose(
@@ -53,95 +113,393 @@ impl AttestationDocVerifierExt for AttestationDoc {
         root_cert_der: &[u8],
         time: Time,
     ) -> Result<AttestationDoc, VerifyError> {
-        let cose = CoseSign1::from_slice(cose_attestation_doc)
-            .map_err(|err| VerifyError::new(ErrorKind::Cose, err))?;
-
-        let payload = cose.payload.as_ref().ok_or(VerifyError::new(
-            ErrorKind::Cose,
-            crate::ErrorContext("Missing Cose payload"),
-        ))?;
-
-        let attestation_doc = AttestationDoc::from_binary(payload).map_err(|_| {
-            VerifyError::new(
-                ErrorKind::AttestationDoc,
-                crate::ErrorContext(
-                    "Failed to decode attestation doc. Cbor deserialization failed.",
-                ),
-            )
-        })?;
-
-        let intermediate_certs = attestation_doc
-            .cabundle
-            .iter()
-            .map(|bytes| CertificateDer::from(bytes.as_slice()))
-            .collect::<Vec<CertificateDer>>();
-        let end_cert = CertificateDer::from(attestation_doc.certificate.as_slice());
-        let root_cert = CertificateDer::from(root_cert_der);
-
-        ChainVerifier::new(&root_cert, intermediate_certs, &end_cert)?.verify(time)?;
-
-        let doc_cert = Certificate::from_der(&attestation_doc.certificate)
-            .map_err(|err| VerifyError::new(ErrorKind::AttestationDoc, err))?;
-        let doc_cert_pub_key = doc_cert.tbs_certificate.subject_public_key_info;
-
-        doc_cert_pub_key
-            .algorithm
-            .assert_algorithm_oid(x509_cert::der::oid::db::rfc5912::ID_EC_PUBLIC_KEY)
-            .map_err(|err| VerifyError::new(ErrorKind::AttestationDoc, err))?;
-
-        let verifying_key =
-            VerifyingKey::from_sec1_bytes(doc_cert_pub_key.subject_public_key.as_bytes().ok_or(
-                VerifyError::new(
-                    ErrorKind::AttestationDoc,
-                    crate::ErrorContext("Attestation doc missing subject_public_key".into()),
-                ),
-            )?)
-            .map_err(|err| VerifyError::new(ErrorKind::AttestationDoc, err))?;
-
-        cose.verify_signature(&[], |signature, msg| {
-            let signature = Signature::try_from(signature)?;
-            verifying_key.verify(msg, &signature)
-        })
-        .map_err(|err| VerifyError::new(ErrorKind::Verification, err))?;
-
-        Ok(attestation_doc)
+        AttestationVerifier::new(root_cert_der.to_vec())
+            .time(time)
+            .verify(cose_attestation_doc)
+    }
+
+    fn from_cose_with_root(
+        cose_attestation_doc: &[u8],
+        root_cert: &Certificate,
+        time: Time,
+    ) -> Result<AttestationDoc, VerifyError> {
+        let root_cert_der = root_cert
+            .to_der()
+            .map_err(|err| VerifyError::new(ErrorKind::RootCertificate, err))?;
+        AttestationVerifier::new(root_cert_der)
+            .time(time)
+            .verify(cose_attestation_doc)
+    }
+
+    fn from_cose_with_nonce(
+        cose_attestation_doc: &[u8],
+        root_cert_der: &[u8],
+        time: Time,
+        expected_nonce: &[u8],
+    ) -> Result<AttestationDoc, VerifyError> {
+        AttestationVerifier::new(root_cert_der.to_vec())
+            .time(time)
+            .nonce(expected_nonce.to_vec())
+            .verify(cose_attestation_doc)
+    }
+
+    fn from_cose_with_pcrs(
+        cose_attestation_doc: &[u8],
+        root_cert_der: &[u8],
+        time: Time,
+        expected_pcrs: &BTreeMap<PcrIndex, Pcr>,
+    ) -> Result<AttestationDoc, VerifyError> {
+        AttestationVerifier::new(root_cert_der.to_vec())
+            .time(time)
+            .expected_pcrs(expected_pcrs.clone())
+            .verify(cose_attestation_doc)
     }
 }
 
 #[cfg(all(test, not(target_arch = "wasm32"), feature = "pki"))]
 mod tests {
     use nsm_nitro_enclave_utils_keygen::DerEncodeExt;
+    use std::collections::BTreeMap;
     use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-    use crate::api::nsm::{AttestationDoc, Digest};
+    use crate::api::{nsm::AttestationDoc, AttestationDocBuilder};
     use crate::driver::dev::sign::AttestationDocSignerExt;
-    use crate::pcr::Pcrs;
+    use crate::pcr::{Pcr, PcrIndex, Pcrs};
     use crate::time::Time;
-    use crate::verify::AttestationDocVerifierExt;
+    use crate::verify::{AttestationDocVerifierExt, ErrorKind};
 
     #[test]
     fn sign_and_verify() {
         let cert_valid_until = Duration::from_secs(60 * 10);
         let cert_chain = nsm_nitro_enclave_utils_keygen::NsmCertChain::generate(cert_valid_until);
 
-        let doc = AttestationDoc {
-            module_id: "".to_string(),
-            digest: Digest::SHA384,
-            timestamp: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            pcrs: Pcrs::default().into(),
-            certificate: cert_chain.end_signer.cert.to_der().unwrap().into(),
-            cabundle: vec![cert_chain.int.to_der().unwrap().into()],
-            public_key: None,
-            user_data: None,
-            nonce: None,
+        let doc = AttestationDocBuilder::new(cert_chain.end_signer.cert.to_der().unwrap().into())
+            .timestamp(Time::default().time())
+            .cabundle(vec![cert_chain.int_signer().cert.to_der().unwrap().into()])
+            .build();
+
+        let doc = doc
+            .sign(cert_chain.end_signer.signing_key.as_p384().unwrap().clone())
+            .unwrap();
+
+        AttestationDoc::from_cose(
+            &doc,
+            &cert_chain.root_signer.cert.to_der().unwrap(),
+            Time::default(),
+        )
+        .unwrap();
+        AttestationDoc::from_cose_with_root(&doc, &cert_chain.root_signer.cert, Time::default())
+            .unwrap();
+    }
+
+    #[test]
+    fn corrupted_signature() {
+        let cert_chain =
+            nsm_nitro_enclave_utils_keygen::NsmCertChain::generate(Duration::from_secs(60));
+
+        let doc = AttestationDocBuilder::new(cert_chain.end_signer.cert.to_der().unwrap().into())
+            .cabundle(vec![cert_chain.int_signer().cert.to_der().unwrap().into()])
+            .build()
+            .sign_corrupted(cert_chain.end_signer.signing_key.as_p384().unwrap().clone())
+            .unwrap();
+
+        let err = AttestationDoc::from_cose(
+            &doc,
+            &cert_chain.root_signer.cert.to_der().unwrap(),
+            Time::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::Verification);
+    }
+
+    /// [`Time`], the attestation document's `timestamp`, and the verification time are all milliseconds since Unix Epoch
+    #[test]
+    fn timestamp_is_milliseconds() {
+        use crate::api::SecretKey;
+        use crate::driver::dev::DevNitro;
+        use crate::verify::AttestationVerifier;
+
+        let cert_chain =
+            nsm_nitro_enclave_utils_keygen::NsmCertChain::generate(Duration::from_secs(60));
+        let verifier = AttestationVerifier::new(cert_chain.root_signer.cert.to_der().unwrap())
+            .timestamp_within_validity();
+        let signing_key = cert_chain.end_signer.signing_key.as_p384().unwrap();
+        let attest = |time: Time| {
+            DevNitro::builder(
+                SecretKey::from(signing_key.as_nonzero_scalar()),
+                cert_chain.end_signer.cert.to_der().unwrap().into(),
+            )
+            .ca_bundle(vec![cert_chain.int_signer().cert.to_der().unwrap().into()])
+            .get_timestamp(time)
+            .build()
+            .attest(None, None, None)
+            .unwrap()
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+        let doc = verifier.verify(&attest(Time::default())).unwrap();
+        let timestamp = Duration::from_millis(doc.timestamp);
+        assert!(timestamp.abs_diff(now) < Duration::from_secs(60));
+
+        // A timestamp in seconds is read as milliseconds, placing the document in 1970
+        let err = verifier
+            .verify(&attest(Time::fixed(now.as_secs())))
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::Timestamp);
+
+        let day = Duration::from_secs(60 * 60 * 24);
+        for time in [now + day, now - day] {
+            let err = verifier
+                .verify(&attest(Time::fixed(time.as_millis() as u64)))
+                .unwrap_err();
+            assert_eq!(err.kind(), &ErrorKind::Timestamp);
+        }
+
+        // The chain is verified at the verifier's time, also in milliseconds
+        let err = AttestationVerifier::new(cert_chain.root_signer.cert.to_der().unwrap())
+            .time(Time::fixed((now + day).as_millis() as u64))
+            .verify(&attest(Time::default()))
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::CertificateExpired);
+    }
+
+    #[test]
+    fn verify_nonce() {
+        let cert_chain =
+            nsm_nitro_enclave_utils_keygen::NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+
+        let sign = |nonce: Option<&[u8]>| {
+            AttestationDoc {
+                nonce: nonce.map(|nonce| nonce.to_vec().into()),
+                ..AttestationDocBuilder::new(cert_chain.end_signer.cert.to_der().unwrap().into())
+                    .cabundle(vec![cert_chain.int_signer().cert.to_der().unwrap().into()])
+                    .build()
+            }
+            .sign(cert_chain.end_signer.signing_key.as_p384().unwrap().clone())
+            .unwrap()
         };
 
-        let doc = doc.sign(cert_chain.end_signer.signing_key).unwrap();
+        let doc = sign(Some(b"nonce"));
+        AttestationDoc::from_cose_with_nonce(&doc, &root, Time::default(), b"nonce").unwrap();
+
+        let err = AttestationDoc::from_cose_with_nonce(&doc, &root, Time::default(), b"other")
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::Nonce);
+
+        let doc = sign(None);
+        let err = AttestationDoc::from_cose_with_nonce(&doc, &root, Time::default(), b"nonce")
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::Nonce);
+    }
 
-        AttestationDoc::from_cose(&doc, &cert_chain.root.to_der().unwrap(), Time::default())
+    #[test]
+    fn verify_pcrs() {
+        let cert_chain =
+            nsm_nitro_enclave_utils_keygen::NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+
+        let mut pcrs = Pcrs::default();
+        pcrs.set(PcrIndex::Zero, Pcr::from([1; 48])).unwrap();
+        pcrs.set(PcrIndex::Eight, Pcr::from([8; 48])).unwrap();
+
+        let doc = AttestationDocBuilder::new(cert_chain.end_signer.cert.to_der().unwrap().into())
+            .pcrs(pcrs)
+            .cabundle(vec![cert_chain.int_signer().cert.to_der().unwrap().into()])
+            .build()
+            .sign(cert_chain.end_signer.signing_key.as_p384().unwrap().clone())
+            .unwrap();
+
+        // Unpinned indexes are ignored
+        let expected = BTreeMap::from([
+            (PcrIndex::Zero, Pcr::from([1; 48])),
+            (PcrIndex::Eight, Pcr::from([8; 48])),
+        ]);
+        AttestationDoc::from_cose_with_pcrs(&doc, &root, Time::default(), &expected).unwrap();
+        AttestationDoc::from_cose_with_pcrs(&doc, &root, Time::default(), &BTreeMap::new())
+            .unwrap();
+
+        let expected = BTreeMap::from([
+            (PcrIndex::Zero, Pcr::from([1; 48])),
+            (PcrIndex::One, Pcr::from([1; 48])),
+            (PcrIndex::Eight, Pcr::from([8; 32])),
+        ]);
+        let err = AttestationDoc::from_cose_with_pcrs(&doc, &root, Time::default(), &expected)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::Pcr);
+        assert!(format!("{err:?}").contains("[One, Eight]"));
+    }
+
+    #[test]
+    fn not_yet_valid_chain() {
+        let hour = Duration::from_secs(60 * 60);
+        let cert_chain = nsm_nitro_enclave_utils_keygen::NsmCertChain::builder(hour * 2)
+            .not_before(SystemTime::now() + hour)
+            .build()
+            .unwrap();
+
+        let doc = AttestationDocBuilder::new(cert_chain.end_signer.cert.to_der().unwrap().into())
+            .cabundle(vec![cert_chain.int_signer().cert.to_der().unwrap().into()])
+            .build();
+
+        let doc = doc
+            .sign(cert_chain.end_signer.signing_key.as_p384().unwrap().clone())
+            .unwrap();
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+
+        let err = AttestationDoc::from_cose(&doc, &root, Time::default()).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::CertificateNotYetValid);
+
+        let in_two_hours = SystemTime::now() + hour * 2;
+        let in_two_hours = in_two_hours.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        AttestationDoc::from_cose(&doc, &root, Time::fixed(in_two_hours)).unwrap();
+    }
+
+    #[test]
+    fn expired_chain() {
+        let cert_chain = nsm_nitro_enclave_utils_keygen::NsmCertChain::generate_expired();
+
+        let doc = AttestationDocBuilder::new(cert_chain.end_signer.cert.to_der().unwrap().into())
+            .cabundle(vec![cert_chain.int_signer().cert.to_der().unwrap().into()])
+            .build();
+
+        let doc = doc
+            .sign(cert_chain.end_signer.signing_key.as_p384().unwrap().clone())
+            .unwrap();
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+
+        let err = AttestationDoc::from_cose(&doc, &root, Time::default()).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::CertificateExpired);
+    }
+
+    #[test]
+    fn multiple_intermediates() {
+        let cert_chain =
+            nsm_nitro_enclave_utils_keygen::NsmCertChain::builder(Duration::from_secs(60))
+                .intermediate_count(3)
+                .build()
+                .unwrap();
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+
+        // Authentic attestation documents lead their cabundle with the root certificate
+        let mut cabundle = vec![root.clone().into()];
+        for int_cert in cert_chain.int_certs() {
+            cabundle.push(int_cert.to_der().unwrap().into());
+        }
+
+        let doc = AttestationDocBuilder::new(cert_chain.end_signer.cert.to_der().unwrap().into())
+            .cabundle(cabundle)
+            .build();
+
+        let doc = doc
+            .sign(cert_chain.end_signer.signing_key.as_p384().unwrap().clone())
             .unwrap();
+
+        AttestationDoc::from_cose(&doc, &root, Time::default()).unwrap();
+    }
+
+    #[test]
+    fn issued_leaf_verifies_against_existing_root() {
+        let cert_chain =
+            nsm_nitro_enclave_utils_keygen::NsmCertChain::generate(Duration::from_secs(60));
+        let leaf = cert_chain.issue_leaf(Duration::from_secs(60)).unwrap();
+
+        let doc = AttestationDocBuilder::new(leaf.cert.to_der().unwrap().into())
+            .cabundle(vec![cert_chain.int_signer().cert.to_der().unwrap().into()])
+            .build();
+
+        let doc = doc
+            .sign(leaf.signing_key.as_p384().unwrap().clone())
+            .unwrap();
+
+        AttestationDoc::from_cose(
+            &doc,
+            &cert_chain.root_signer.cert.to_der().unwrap(),
+            Time::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn verify_cert_chain() {
+        let cert_chain =
+            nsm_nitro_enclave_utils_keygen::NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+        let int = cert_chain.int_signer().cert.to_der().unwrap();
+        let end = cert_chain.end_signer.cert.to_der().unwrap();
+
+        super::verify_cert_chain(&root, &[int.clone()], &end, Time::default()).unwrap();
+
+        let err = super::verify_cert_chain(&root, &[], &end, Time::default()).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::UnknownIssuer);
+
+        let later = Time::fixed(Time::default().time() + 60 * 60 * 1000);
+        let err = super::verify_cert_chain(&root, &[int], &end, later).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::CertificateExpired);
+    }
+
+    #[test]
+    fn owned_chain_verifier() {
+        use super::OwnedChainVerifier;
+
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+        assert_send_sync::<OwnedChainVerifier>();
+
+        let cert_chain =
+            nsm_nitro_enclave_utils_keygen::NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+        let int = cert_chain.int_signer().cert.to_der().unwrap();
+        let verifier = OwnedChainVerifier::new(vec![root.clone()]).unwrap();
+
+        // Reused for chains with different leaves
+        let leaf = cert_chain.issue_leaf(Duration::from_secs(60)).unwrap();
+        for end in [&cert_chain.end_signer.cert, &leaf.cert] {
+            verifier
+                .verify(&[int.clone()], &end.to_der().unwrap(), Time::default())
+                .unwrap();
+        }
+
+        let end = cert_chain.end_signer.cert.to_der().unwrap();
+        let crl = cert_chain.generate_crl().unwrap().to_der().unwrap();
+        let err = OwnedChainVerifier::new(vec![root])
+            .unwrap()
+            .crls(&[crl])
+            .unwrap()
+            .verify(&[int], &end, Time::default())
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::CertificateRevoked);
+
+        let err = OwnedChainVerifier::new(vec![]).err().unwrap();
+        assert_eq!(err.kind(), &ErrorKind::RootCertificate);
+        let err = OwnedChainVerifier::new(vec![b"-----BEGIN CERTIFICATE-----".to_vec()])
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), &ErrorKind::RootCertificate);
+    }
+
+    #[test]
+    fn p256_chain_is_rejected() {
+        use super::cert::ChainVerifier;
+        use nsm_nitro_enclave_utils_keygen::KeyAlgorithm;
+        use webpki::types::CertificateDer;
+
+        let cert_chain =
+            nsm_nitro_enclave_utils_keygen::NsmCertChain::builder(Duration::from_secs(60))
+                .key_algorithm(KeyAlgorithm::P256)
+                .build()
+                .unwrap();
+
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+        let int = cert_chain.int_signer().cert.to_der().unwrap();
+        let end = cert_chain.end_signer.cert.to_der().unwrap();
+
+        let root = CertificateDer::from(root.as_slice());
+        let end = CertificateDer::from(end.as_slice());
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let err = ChainVerifier::new(&[root], vec![CertificateDer::from(int.as_slice())], &end)
+            .unwrap()
+            .verify(now, Duration::ZERO)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::UnsupportedSignatureAlgorithm);
     }
 }
@@ -0,0 +1,1398 @@
+//! Provides [`AttestationVerifier`], which allows every check performed on an attestation document to be configured before verifying it.
+
+use coset::{iana, CborSerializable, CoseSign1};
+use p384::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use p384::pkcs8::DecodePublicKey;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use subtle::ConstantTimeEq;
+use webpki::types::{CertificateDer, TrustAnchor};
+use x509_cert::{der::Decode, Certificate};
+
+use super::cert::{chain_validity, owned_trust_anchors, ChainVerifier, Validity};
+use super::{ErrorKind, KeyUsage, VerifyError, MAX_DOCUMENT_SIZE};
+use crate::api::nsm::{AttestationDoc, Digest};
+use crate::pcr::{Pcr, PcrIndex};
+use crate::time::Time;
+
+/// A builder-style verifier for attestation documents.
+///
+/// ```rust,no_run
+/// use nsm_nitro_enclave_utils::{
+///     pcr::{Pcr, PcrIndex},
+///     verify::AttestationVerifier,
+/// };
+/// use std::collections::BTreeMap;
+///
+/// let root_cert_der = include_bytes!("../../../test_data/root-certificate.der").to_vec();
+/// let nonce = b"client nonce".to_vec();
+/// let expected_pcrs = BTreeMap::from([(PcrIndex::Zero, Pcr::from([0; 48]))]);
+/// let cose_attestation_doc = std::fs::read("attestation_doc.cose").unwrap();
+///
+/// let attestation_doc = AttestationVerifier::new(root_cert_der)
+///     .nonce(nonce)
+///     .expected_pcrs(expected_pcrs)
+///     .verify(&cose_attestation_doc)
+///     .unwrap();
+/// ```
+///
+/// #### Verification process
+/// This implements the 4 steps outlined in the AWS Nitro Enclaves ["verify root" documentation](https://docs.aws.amazon.com/enclaves/latest/user/verify-root.html)
+/// 1. Decode the CBOR object and map it to a COSE_Sign1 structure.
+/// 2. Extract the attestation document from the COSE_Sign1 structure.
+/// 3. Verify the certificate chain.
+/// 4. Ensure that the attestation document is properly signed.
+///
+/// Once the attestation document has been verified, its `digest` is checked against the COSE signing algorithm, then its nonce, user data and PCRs are compared to the expected values, if any were provided.
+///
+/// #### Async usage
+/// [`AttestationVerifier`] is `Send + Sync`, and can be constructed once and shared between tasks, i.e. behind an `Arc`.
+/// [`AttestationVerifier::verify`] doesn't perform any I/O. It is CPU-bound, but cheap: a handful of ECDSA P-384 signature verifications, typically well under a millisecond each.
+/// Calling it directly from an async request handler is fine for most services. If your executor is sensitive to any blocking, move it onto a blocking thread,
+/// i.e. `tokio::task::spawn_blocking(move || verifier.verify(&cose_attestation_doc))`, where `verifier` is an `Arc<AttestationVerifier>`.
+pub struct AttestationVerifier {
+    root_certs: Vec<Vec<u8>>,
+    /// `root_certs`, parsed once on construction. `None` if any of them failed to parse, the error is reported by [`AttestationVerifier::verify`].
+    root_anchors: Option<Vec<TrustAnchor<'static>>>,
+    expected_pcrs: BTreeMap<PcrIndex, Pcr>,
+    nonce: Option<Vec<u8>>,
+    user_data: Option<UserDataMatcher>,
+    time: Option<Time>,
+    allow_skew: Duration,
+    crls: Vec<Vec<u8>>,
+    insecure_ignore_expiry: bool,
+    key_usage: KeyUsage,
+    deny_debug_mode: bool,
+    timestamp_within_validity: bool,
+    module_id: Option<ModuleIdMatcher>,
+    max_document_size: usize,
+}
+
+impl AttestationVerifier {
+    /// `root_cert`: the DER encoded root certificate the attestation document's certificate chain must verify against.
+    /// This is either AWS's root certificate, or your own when using "bring your own pki".
+    /// AWS distributes its root certificate PEM encoded, it must be converted to DER first. Verification fails with [`ErrorKind::RootCertificate`] when given a PEM encoded certificate.
+    pub fn new(root_cert: Vec<u8>) -> Self {
+        Self::with_roots(vec![root_cert])
+    }
+
+    /// `root_certs`: DER encoded root certificates, the attestation document's certificate chain must verify against at least one of them.
+    /// Useful during a root certificate rotation, or to accept both AWS's root certificate and your own with a single verifier.
+    /// The root certificates are parsed once, here, rather than on every verification.
+    pub fn with_roots(root_certs: Vec<Vec<u8>>) -> Self {
+        Self {
+            root_anchors: owned_trust_anchors(&root_certs).ok(),
+            root_certs,
+            expected_pcrs: BTreeMap::new(),
+            nonce: None,
+            user_data: None,
+            time: None,
+            allow_skew: Duration::ZERO,
+            crls: Vec::new(),
+            insecure_ignore_expiry: false,
+            key_usage: KeyUsage::server_auth(),
+            deny_debug_mode: false,
+            timestamp_within_validity: false,
+            module_id: None,
+            max_document_size: MAX_DOCUMENT_SIZE,
+        }
+    }
+
+    /// Require each PCR to match the attestation document's PCR at the same index. Indexes that aren't provided are not checked.
+    /// Verification fails with [`ErrorKind::Pcr`] if any of them differ.
+    pub fn expected_pcrs(self, expected_pcrs: BTreeMap<PcrIndex, Pcr>) -> Self {
+        Self {
+            expected_pcrs,
+            ..self
+        }
+    }
+
+    /// Require the attestation document's nonce to match `nonce`.
+    /// Verification fails with [`ErrorKind::Nonce`] if it is missing or doesn't match.
+    pub fn nonce(self, nonce: Vec<u8>) -> Self {
+        Self {
+            nonce: Some(nonce),
+            ..self
+        }
+    }
+
+    /// Require the attestation document's `user_data` to match `user_data`, i.e. a hash of the request the attestation document was produced for.
+    /// Verification fails with [`ErrorKind::UserData`] if it is missing or doesn't match.
+    pub fn user_data(self, user_data: Vec<u8>) -> Self {
+        Self {
+            user_data: Some(UserDataMatcher::Exact(user_data)),
+            ..self
+        }
+    }
+
+    /// Behaves like [`AttestationVerifier::user_data`], requiring `matches` to accept the attestation document's `user_data`, i.e. to check a prefix or a field of JSON encoded user data.
+    /// Unlike [`AttestationVerifier::user_data`], the comparison isn't guaranteed to be constant time.
+    pub fn user_data_matches(
+        self,
+        matches: impl Fn(&[u8]) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            user_data: Some(UserDataMatcher::Predicate(Box::new(matches))),
+            ..self
+        }
+    }
+
+    /// Require the attestation document's `module_id` to be exactly `module_id`, pinning the attestation document to a specific enclave.
+    /// Verification fails with [`ErrorKind::ModuleId`] if it doesn't match.
+    pub fn module_id(self, module_id: String) -> Self {
+        Self {
+            module_id: Some(ModuleIdMatcher::Exact(module_id)),
+            ..self
+        }
+    }
+
+    /// Behaves like [`AttestationVerifier::module_id`], requiring the attestation document's `module_id` to start with `prefix`.
+    /// AWS module ids combine the parent instance id and the enclave id, i.e. `i-0123456789abcdef0-enc0123456789abcdef`.
+    pub fn module_id_prefix(self, prefix: String) -> Self {
+        Self {
+            module_id: Some(ModuleIdMatcher::Prefix(prefix)),
+            ..self
+        }
+    }
+
+    /// Set the maximum size, in bytes, of the COSE encoded attestation documents that are accepted. Defaults to [`MAX_DOCUMENT_SIZE`].
+    /// Larger documents fail with [`ErrorKind::DocumentSize`] before they're parsed, bounding the memory spent on untrusted input.
+    pub fn max_document_size(self, max_document_size: usize) -> Self {
+        Self {
+            max_document_size,
+            ..self
+        }
+    }
+
+    /// Set the [`Time`] the certificate chain is verified at. Defaults to [`Time::system_time`].
+    /// When targeting WebAssembly it defaults to `Time::js_date` with the `js-date` feature enabled, and must be provided otherwise.
+    pub fn time(self, time: Time) -> Self {
+        Self {
+            time: Some(time),
+            ..self
+        }
+    }
+
+    /// Accept a certificate chain that is expired, or not yet valid, by up to `allow_skew`. Defaults to zero.
+    /// Useful when the clock of the verifying machine can't be trusted to match the enclave's.
+    pub fn allow_skew(self, allow_skew: Duration) -> Self {
+        Self { allow_skew, ..self }
+    }
+
+    /// Check the revocation status of every certificate in the chain against the DER encoded certificate revocation lists. Defaults to none, disabling revocation checks.
+    /// A certificate that isn't covered by any of the CRLs is treated as not revoked.
+    ///
+    /// This is intended for "bring your own pki" deployments that publish CRLs.
+    /// AWS's documentation requires that ["CRL must be disabled when doing the validation"](https://docs.aws.amazon.com/enclaves/latest/user/verify-root.html#chain) of authentic attestation documents.
+    pub fn crls(self, crls: Vec<Vec<u8>>) -> Self {
+        Self { crls, ..self }
+    }
+
+    /// Reject attestation documents produced by an enclave running in debug mode, whose PCR0 is entirely zero.
+    /// Verification fails with [`ErrorKind::DebugMode`] if PCR0 is missing or all zeros.
+    ///
+    /// Note that [`Pcrs::zeros`](crate::pcr::Pcrs::zeros), the default for the dev driver, produces documents that are rejected by this.
+    pub fn deny_debug_mode(self) -> Self {
+        Self {
+            deny_debug_mode: true,
+            ..self
+        }
+    }
+
+    /// Require the attestation document's `timestamp` to fall within the validity period of its certificate.
+    /// Verification fails with [`ErrorKind::Timestamp`] otherwise, even if the certificate chain is valid at the verification [`Time`].
+    ///
+    /// The Nitro Secure Module only signs with a certificate that is valid at the time of signing, a `timestamp` outside of it indicates a forged or misconfigured document.
+    pub fn timestamp_within_validity(self) -> Self {
+        Self {
+            timestamp_within_validity: true,
+            ..self
+        }
+    }
+
+    /// Set the extended key usage the end certificate must assert. Defaults to [`KeyUsage::server_auth`], which only requires `serverAuth` when the end certificate includes an extended key usage extension.
+    /// Authentic AWS end certificates, and those generated by `nsm-nitro-enclave-utils-keygen`, don't include the extension and are accepted by the default.
+    /// Verification fails with [`ErrorKind::KeyUsage`] if the end certificate doesn't assert the required usage.
+    pub fn key_usage(self, key_usage: KeyUsage) -> Self {
+        Self { key_usage, ..self }
+    }
+
+    /// **Only use this for testing.** If the certificate chain is expired, or not yet valid, at the current [`Time`], verify it again at a moment every certificate in the attestation document is valid.
+    /// Signatures and the chain's structure are still verified, but an expired, or not yet valid, certificate chain is accepted.
+    ///
+    /// This exists for replaying attestation documents recorded as test fixtures, whose certificates expire long before the fixture is retired.
+    /// An attacker holding an old attestation document, signed by a certificate that has since expired, would be able to replay it to a verifier using this.
+    pub fn insecure_ignore_expiry(self) -> Self {
+        Self {
+            insecure_ignore_expiry: true,
+            ..self
+        }
+    }
+
+    /// Verifies a COSE encoded attestation document, returning the decoded [`AttestationDoc`] if every check passes.
+    pub fn verify(&self, cose_attestation_doc: &[u8]) -> Result<AttestationDoc, VerifyError> {
+        self.verify_detailed(cose_attestation_doc)
+            .map(|verified| verified.attestation_doc)
+    }
+
+    /// Behaves like [`AttestationVerifier::verify`], additionally returning what verification learned along the way, see [`VerifiedAttestationDoc`]:
+    /// the COSE protected header the attestation document was signed with, its decoded certificate, that certificate's validity, and the validity of the whole chain.
+    pub fn verify_detailed(
+        &self,
+        cose_attestation_doc: &[u8],
+    ) -> Result<VerifiedAttestationDoc, VerifyError> {
+        if cose_attestation_doc.len() > self.max_document_size {
+            return Err(VerifyError::new(
+                ErrorKind::DocumentSize,
+                crate::ErrorContext("Attestation doc exceeds the maximum document size"),
+            ));
+        }
+
+        let cose = CoseSign1::from_slice(cose_attestation_doc)
+            .map_err(|err| VerifyError::new(ErrorKind::Cose, err))?;
+
+        // The signature is always verified as ES384, regardless of what the header claims, but a document claiming any other algorithm isn't authentic
+        if cose.protected.header.alg != Some(coset::Algorithm::Assigned(iana::Algorithm::ES384)) {
+            return Err(VerifyError::new(
+                ErrorKind::Cose,
+                crate::ErrorContext("Cose protected header algorithm must be ES384"),
+            ));
+        }
+
+        let payload = cose.payload.as_ref().ok_or(VerifyError::new(
+            ErrorKind::Cose,
+            crate::ErrorContext("Missing Cose payload"),
+        ))?;
+
+        let attestation_doc = AttestationDoc::from_binary(payload).map_err(|_| {
+            VerifyError::new(
+                ErrorKind::AttestationDoc,
+                crate::ErrorContext(
+                    "Failed to decode attestation doc. Cbor deserialization failed.",
+                ),
+            )
+        })?;
+
+        let intermediate_certs = attestation_doc
+            .cabundle
+            .iter()
+            .map(|bytes| CertificateDer::from(bytes.as_slice()))
+            .collect::<Vec<CertificateDer>>();
+        let end_cert = CertificateDer::from(attestation_doc.certificate.as_slice());
+        let root_anchors = match &self.root_anchors {
+            Some(root_anchors) => Cow::Borrowed(root_anchors.as_slice()),
+            // Parsing failed on construction, parsing again reports why
+            None => Cow::Owned(owned_trust_anchors(&self.root_certs)?),
+        };
+
+        let chain = ChainVerifier::with_anchors(&root_anchors, intermediate_certs, &end_cert)?
+            .with_crls(&self.crls)?
+            .with_key_usage(self.key_usage);
+        let chain_validity = match chain.verify(self.now()?, self.allow_skew) {
+            Err(err)
+                if self.insecure_ignore_expiry
+                    && matches!(
+                        err.kind(),
+                        ErrorKind::CertificateExpired | ErrorKind::CertificateNotYetValid
+                    ) =>
+            {
+                // The chain must still verify at the moment taken from its unverified certificates, which authenticates their validity periods
+                let overlap = validity_overlap(&attestation_doc).ok_or(err)?;
+                chain.verify(overlap, Duration::ZERO)?
+            }
+            result => result?,
+        };
+
+        let doc_cert = Certificate::from_der(&attestation_doc.certificate)
+            .map_err(|err| VerifyError::new(ErrorKind::AttestationDoc, err))?;
+        let leaf_validity = Validity::of(&doc_cert);
+        let doc_cert_pub_key = &doc_cert.tbs_certificate.subject_public_key_info;
+
+        doc_cert_pub_key
+            .algorithm
+            .assert_algorithm_oid(x509_cert::der::oid::db::rfc5912::ID_EC_PUBLIC_KEY)
+            .map_err(|err| VerifyError::new(ErrorKind::AttestationDoc, err))?;
+
+        let verifying_key =
+            VerifyingKey::from_sec1_bytes(doc_cert_pub_key.subject_public_key.as_bytes().ok_or(
+                VerifyError::new(
+                    ErrorKind::AttestationDoc,
+                    crate::ErrorContext("Attestation doc missing subject_public_key"),
+                ),
+            )?)
+            .map_err(|err| VerifyError::new(ErrorKind::AttestationDoc, err))?;
+
+        cose.verify_signature(&[], |signature, msg| {
+            let signature = Signature::try_from(signature)?;
+            verifying_key.verify(msg, &signature)
+        })
+        .map_err(|err| VerifyError::new(ErrorKind::Verification, err))?;
+
+        verify_digest(&attestation_doc)?;
+        if let Some(nonce) = &self.nonce {
+            verify_nonce(&attestation_doc, nonce)?;
+        }
+        if let Some(user_data) = &self.user_data {
+            verify_user_data(&attestation_doc, user_data)?;
+        }
+        verify_pcrs(&attestation_doc, &self.expected_pcrs)?;
+        if self.deny_debug_mode {
+            verify_not_debug_mode(&attestation_doc)?;
+        }
+        if self.timestamp_within_validity {
+            verify_timestamp(&attestation_doc, &leaf_validity)?;
+        }
+        if let Some(module_id) = &self.module_id {
+            verify_module_id(&attestation_doc, module_id)?;
+        }
+
+        Ok(VerifiedAttestationDoc {
+            attestation_doc,
+            protected: cose.protected.header,
+            leaf: doc_cert,
+            leaf_validity,
+            chain_validity,
+        })
+    }
+
+    /// The current time, as the duration since Unix Epoch
+    fn now(&self) -> Result<Duration, VerifyError> {
+        match &self.time {
+            Some(time) => Ok(time.duration()),
+            #[cfg(any(not(target_arch = "wasm32"), feature = "js-date"))]
+            None => Ok(Time::default().duration()),
+            #[cfg(all(target_arch = "wasm32", not(feature = "js-date")))]
+            None => Err(VerifyError::new(
+                ErrorKind::Verification,
+                crate::ErrorContext(
+                    "A Time must be provided when targeting WebAssembly without the js-date feature",
+                ),
+            )),
+        }
+    }
+}
+
+/// A verified [`AttestationDoc`] along with the details of its verification, returned by [`AttestationVerifier::verify_detailed`]
+#[derive(Debug, Clone)]
+pub struct VerifiedAttestationDoc {
+    /// The decoded attestation document, the same value [`AttestationVerifier::verify`] returns
+    pub attestation_doc: AttestationDoc,
+    /// The COSE protected header the attestation document was signed with, see [`VerifiedAttestationDoc::protected_algorithm`] and [`VerifiedAttestationDoc::protected_key_id`]
+    pub protected: coset::Header,
+    /// The attestation document's certificate, already decoded during verification to read its public key
+    pub leaf: Certificate,
+    /// The validity period of the attestation document's certificate
+    pub leaf_validity: Validity,
+    /// The period during which every certificate the chain was verified through, the attestation document's `certificate` and the intermediates of its `cabundle`, is valid.
+    /// Alert on `not_after` to learn when the attestation document stops verifying.
+    pub chain_validity: Validity,
+}
+
+impl VerifiedAttestationDoc {
+    /// The signing algorithm claimed by the COSE protected header. Verification only succeeds for ES384.
+    pub fn protected_algorithm(&self) -> Option<&coset::Algorithm> {
+        self.protected.alg.as_ref()
+    }
+
+    /// The COSE `kid` protected header, identifying the key that signed the attestation document.
+    /// Authentic attestation documents don't set it, in which case this is `None`.
+    pub fn protected_key_id(&self) -> Option<&[u8]> {
+        (!self.protected.key_id.is_empty()).then_some(self.protected.key_id.as_slice())
+    }
+
+    /// The attestation document's `digest`, the same value as `attestation_doc.digest` in [`AttestationVerifier::verify`]'s result.
+    ///
+    /// The `digest` names the hash family of the COSE signature, not of the PCRs: the verifier only accepts ES384 signatures, so it rejects any `digest` other than [`Digest::SHA384`] with [`ErrorKind::Digest`],
+    /// whatever the [`PcrDigest`](crate::pcr::PcrDigest) bank of the PCRs. Size PCR buffers from the PCRs themselves.
+    pub fn digest(&self) -> Digest {
+        self.attestation_doc.digest
+    }
+
+    /// The `module_id` of the Nitro Secure Module that produced the attestation document, see [`AttestationVerifier::module_id`] to pin it
+    pub fn module_id(&self) -> &str {
+        &self.attestation_doc.module_id
+    }
+
+    /// The enclave's public key, as provided in the `Attestation` request.
+    /// Typically used to encrypt data that only the attested enclave can decrypt.
+    pub fn enclave_public_key(&self) -> Option<&[u8]> {
+        self.attestation_doc
+            .public_key
+            .as_ref()
+            .map(|public_key| public_key.as_slice())
+    }
+
+    /// Parses [`VerifiedAttestationDoc::enclave_public_key`] as a P-384 key, either SEC1 or DER encoded `SubjectPublicKeyInfo`.
+    /// Returns `Ok(None)` when the attestation document doesn't include a public key.
+    pub fn enclave_verifying_key(&self) -> Result<Option<VerifyingKey>, VerifyError> {
+        let Some(public_key) = self.enclave_public_key() else {
+            return Ok(None);
+        };
+
+        VerifyingKey::from_sec1_bytes(public_key)
+            .or_else(|_| VerifyingKey::from_public_key_der(public_key))
+            .map(Some)
+            .map_err(|_| {
+                VerifyError::new(
+                    ErrorKind::AttestationDoc,
+                    crate::ErrorContext(
+                        "Attestation doc public_key is not a SEC1 or SubjectPublicKeyInfo encoded P-384 key",
+                    ),
+                )
+            })
+    }
+}
+
+/// The earliest moment every certificate in the attestation document, its `cabundle` and `certificate`, is valid, used by [`AttestationVerifier::insecure_ignore_expiry`].
+/// `None` if any of them can't be parsed, or their validity periods don't overlap.
+fn validity_overlap(attestation_doc: &AttestationDoc) -> Option<Duration> {
+    let validity = chain_validity(
+        attestation_doc
+            .cabundle
+            .iter()
+            .chain([&attestation_doc.certificate])
+            .map(|bytes| bytes.as_slice()),
+    )
+    .ok()?;
+
+    (validity.not_before <= validity.not_after).then_some(validity.not_before)
+}
+
+/// Compares the attestation document's nonce in constant time
+fn verify_nonce(
+    attestation_doc: &AttestationDoc,
+    expected_nonce: &[u8],
+) -> Result<(), VerifyError> {
+    let nonce = attestation_doc.nonce.as_ref().ok_or(VerifyError::new(
+        ErrorKind::Nonce,
+        crate::ErrorContext("Attestation doc missing nonce"),
+    ))?;
+
+    if !bool::from(nonce.as_slice().ct_eq(expected_nonce)) {
+        return Err(VerifyError::new(
+            ErrorKind::Nonce,
+            crate::ErrorContext("Attestation doc nonce does not match the expected nonce"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Accepts or rejects the attestation document's `user_data`
+type UserDataPredicate = Box<dyn Fn(&[u8]) -> bool + Send + Sync>;
+
+/// Configured via [`AttestationVerifier::user_data`] or [`AttestationVerifier::user_data_matches`]
+enum UserDataMatcher {
+    Exact(Vec<u8>),
+    Predicate(UserDataPredicate),
+}
+
+/// Compares the attestation document's user data in constant time, unless matched by a predicate
+fn verify_user_data(
+    attestation_doc: &AttestationDoc,
+    matcher: &UserDataMatcher,
+) -> Result<(), VerifyError> {
+    let user_data = attestation_doc.user_data.as_ref().ok_or(VerifyError::new(
+        ErrorKind::UserData,
+        crate::ErrorContext("Attestation doc missing user_data"),
+    ))?;
+
+    let matches = match matcher {
+        UserDataMatcher::Exact(expected) => bool::from(user_data.as_slice().ct_eq(expected)),
+        UserDataMatcher::Predicate(matches) => matches(user_data),
+    };
+    if !matches {
+        return Err(VerifyError::new(
+            ErrorKind::UserData,
+            crate::ErrorContext("Attestation doc user_data does not match the expected user_data"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Asserts the attestation document's `digest` belongs to the same hash family as the COSE signing algorithm.
+/// The algorithm is always ES384 by the time this is called, so the `digest` must be SHA384, see [`VerifiedAttestationDoc::digest`].
+fn verify_digest(attestation_doc: &AttestationDoc) -> Result<(), VerifyError> {
+    if attestation_doc.digest != Digest::SHA384 {
+        return Err(VerifyError::new(
+            ErrorKind::Digest,
+            crate::ErrorContext("Attestation doc digest does not match the Cose signing algorithm"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Configured via [`AttestationVerifier::module_id`] or [`AttestationVerifier::module_id_prefix`]
+enum ModuleIdMatcher {
+    Exact(String),
+    Prefix(String),
+}
+
+fn verify_module_id(
+    attestation_doc: &AttestationDoc,
+    matcher: &ModuleIdMatcher,
+) -> Result<(), VerifyError> {
+    let module_id = &attestation_doc.module_id;
+    let matches = match matcher {
+        ModuleIdMatcher::Exact(expected) => module_id == expected,
+        ModuleIdMatcher::Prefix(prefix) => module_id.starts_with(prefix.as_str()),
+    };
+    if !matches {
+        return Err(VerifyError::new(
+            ErrorKind::ModuleId,
+            crate::ErrorContext("Attestation doc module_id does not match the expected module_id"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Compares each expected PCR to the attestation document's PCR at the same index in constant time.
+/// A PCR that is absent from the attestation document, or has a different length, is treated as a mismatch.
+fn verify_pcrs(
+    attestation_doc: &AttestationDoc,
+    expected_pcrs: &BTreeMap<PcrIndex, Pcr>,
+) -> Result<(), VerifyError> {
+    let mismatched = expected_pcrs
+        .iter()
+        .filter(|(index, expected)| {
+            attestation_doc
+                .pcrs
+                .get(&usize::from(**index))
+                .and_then(|pcr| Pcr::try_from(pcr.to_vec()).ok())
+                .map_or(true, |pcr| !pcr.ct_eq(expected))
+        })
+        .map(|(index, _)| *index)
+        .collect::<Vec<PcrIndex>>();
+
+    if !mismatched.is_empty() {
+        return Err(VerifyError::new(ErrorKind::Pcr, PcrMismatch(mismatched)));
+    }
+
+    Ok(())
+}
+
+/// Enclaves running in debug mode report PCR0, along with every other PCR, as all zeros
+fn verify_not_debug_mode(attestation_doc: &AttestationDoc) -> Result<(), VerifyError> {
+    let debug_mode = attestation_doc
+        .pcrs
+        .get(&usize::from(PcrIndex::Zero))
+        .map_or(true, |pcr| pcr.iter().all(|byte| *byte == 0));
+
+    if debug_mode {
+        return Err(VerifyError::new(
+            ErrorKind::DebugMode,
+            crate::ErrorContext("Attestation doc was produced by an enclave in debug mode"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// The attestation document's `timestamp` is expressed as milliseconds since Unix Epoch
+fn verify_timestamp(
+    attestation_doc: &AttestationDoc,
+    leaf_validity: &Validity,
+) -> Result<(), VerifyError> {
+    let timestamp = Duration::from_millis(attestation_doc.timestamp);
+
+    if timestamp < leaf_validity.not_before || timestamp > leaf_validity.not_after {
+        return Err(VerifyError::new(
+            ErrorKind::Timestamp,
+            crate::ErrorContext(
+                "Attestation doc timestamp is outside of its certificate's validity period",
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Lists the [`PcrIndex`]es whose values didn't match the expected PCRs
+#[derive(Debug)]
+struct PcrMismatch(Vec<PcrIndex>);
+
+impl std::fmt::Display for PcrMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Attestation doc PCRs do not match the expected PCRs at indexes {:?}",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for PcrMismatch {}
+
+#[cfg(all(test, not(target_arch = "wasm32"), feature = "pki"))]
+mod tests {
+    use coset::{iana, CborSerializable, CoseSign1, CoseSign1Builder, HeaderBuilder};
+    use nsm_nitro_enclave_utils_keygen::{
+        CertificateIssuer, DerEncodeExt, Name, NsmCertChain, SerialNumber,
+    };
+    use p384::ecdsa::{signature::Signer, DerSignature, Signature};
+    use std::collections::BTreeMap;
+    use std::str::FromStr;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use super::{AttestationVerifier, KeyUsage, Validity};
+    use crate::api::{nsm::Digest, AttestationDocBuilder};
+    use crate::driver::dev::sign::AttestationDocSignerExt;
+    use crate::pcr::{Pcr, PcrDigest, PcrIndex, Pcrs};
+    use crate::time::Time;
+    use crate::verify::ErrorKind;
+    use x509_cert::{
+        crl::{CertificateList, RevokedCert, TbsCertList},
+        der::{
+            asn1::{BitString, OctetString, Uint},
+            oid::AssociatedOid,
+            pem::LineEnding,
+            EncodePem,
+        },
+        ext::{pkix::CrlNumber, Extension},
+        spki::DynSignatureAlgorithmIdentifier,
+        Version,
+    };
+
+    /// An attestation document whose certificate and cabundle chain to `cert_chain`'s root
+    fn doc_builder(cert_chain: &NsmCertChain) -> AttestationDocBuilder {
+        AttestationDocBuilder::new(cert_chain.end_signer.cert.to_der().unwrap().into())
+            .cabundle(vec![cert_chain.int_signer().cert.to_der().unwrap().into()])
+    }
+
+    fn end_signing_key(cert_chain: &NsmCertChain) -> p384::ecdsa::SigningKey {
+        cert_chain.end_signer.signing_key.as_p384().unwrap().clone()
+    }
+
+    /// Signs the document built by [`doc_builder`], after `configure` sets the fields under test
+    fn sign_doc(
+        cert_chain: &NsmCertChain,
+        configure: impl FnOnce(AttestationDocBuilder) -> AttestationDocBuilder,
+    ) -> Vec<u8> {
+        configure(doc_builder(cert_chain))
+            .build()
+            .sign(end_signing_key(cert_chain))
+            .unwrap()
+    }
+
+    fn sign(cert_chain: &NsmCertChain, pcrs: Pcrs, nonce: Option<&[u8]>) -> Vec<u8> {
+        sign_doc(cert_chain, |doc| match nonce {
+            Some(nonce) => doc.pcrs(pcrs).nonce(nonce.to_vec().into()),
+            None => doc.pcrs(pcrs),
+        })
+    }
+
+    #[test]
+    fn nonce_and_pcrs() {
+        let cert_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+
+        let mut pcrs = Pcrs::default();
+        pcrs.set(PcrIndex::Two, Pcr::from([2; 48])).unwrap();
+        let doc = sign(&cert_chain, pcrs, Some(b"nonce"));
+
+        let expected_pcrs = BTreeMap::from([(PcrIndex::Two, Pcr::from([2; 48]))]);
+        AttestationVerifier::new(root.clone())
+            .nonce(b"nonce".to_vec())
+            .expected_pcrs(expected_pcrs.clone())
+            .verify(&doc)
+            .unwrap();
+
+        let err = AttestationVerifier::new(root.clone())
+            .nonce(b"other".to_vec())
+            .expected_pcrs(expected_pcrs)
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::Nonce);
+
+        let err = AttestationVerifier::new(root)
+            .nonce(b"nonce".to_vec())
+            .expected_pcrs(BTreeMap::from([(PcrIndex::Two, Pcr::from([0; 48]))]))
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::Pcr);
+    }
+
+    #[test]
+    fn user_data() {
+        let cert_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+        let doc = sign_doc(&cert_chain, |doc| {
+            doc.user_data(b"request-hash".to_vec().into())
+        });
+
+        AttestationVerifier::new(root.clone())
+            .user_data(b"request-hash".to_vec())
+            .verify(&doc)
+            .unwrap();
+        AttestationVerifier::new(root.clone())
+            .user_data_matches(|user_data| user_data.starts_with(b"request-"))
+            .verify(&doc)
+            .unwrap();
+
+        let err = AttestationVerifier::new(root.clone())
+            .user_data(b"other".to_vec())
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::UserData);
+        let err = AttestationVerifier::new(root.clone())
+            .user_data_matches(|_| false)
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::UserData);
+
+        let err = AttestationVerifier::new(root)
+            .user_data(b"request-hash".to_vec())
+            .verify(&sign(&cert_chain, Pcrs::default(), None))
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::UserData);
+    }
+
+    #[test]
+    fn multiple_roots() {
+        let cert_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let other_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+        let other_root = other_chain.root_signer.cert.to_der().unwrap();
+        let doc = sign(&cert_chain, Pcrs::default(), None);
+
+        AttestationVerifier::with_roots(vec![other_root.clone(), root])
+            .verify(&doc)
+            .unwrap();
+
+        // Both chains use the default subjects, so the other root is found as the intermediate's issuer but its signature doesn't match
+        let err = AttestationVerifier::with_roots(vec![other_root])
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::BadSignature);
+
+        let unrelated_root = NsmCertChain::builder(Duration::from_secs(60))
+            .root_subject(Name::from_str("CN=unrelated-root").unwrap())
+            .build()
+            .unwrap()
+            .root_signer
+            .cert
+            .to_der()
+            .unwrap();
+        let err = AttestationVerifier::with_roots(vec![unrelated_root])
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::UnknownIssuer);
+
+        let err = AttestationVerifier::with_roots(vec![])
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::RootCertificate);
+    }
+
+    #[test]
+    fn pem_root_is_rejected() {
+        let cert_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_pem(LineEnding::LF).unwrap();
+        let doc = sign(&cert_chain, Pcrs::default(), None);
+
+        let err = AttestationVerifier::new(root.into_bytes())
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::RootCertificate);
+        assert!(err.to_string().contains("got PEM"));
+    }
+
+    /// Issues a CRL signed by `issuer`, revoking `revoked`
+    fn issue_crl(issuer: &CertificateIssuer, revoked: &[SerialNumber]) -> Vec<u8> {
+        let signing_key = issuer.signing_key.as_ref().unwrap().as_p384().unwrap();
+        let now = x509_cert::time::Time::try_from(SystemTime::now()).unwrap();
+        let next_update =
+            x509_cert::time::Time::try_from(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        let crl_number = Extension {
+            extn_id: CrlNumber::OID,
+            critical: false,
+            extn_value: OctetString::new(CrlNumber(Uint::new(&[1]).unwrap()).to_der().unwrap())
+                .unwrap(),
+        };
+
+        let tbs_cert_list = TbsCertList {
+            version: Version::V2,
+            signature: signing_key.signature_algorithm_identifier().unwrap(),
+            issuer: issuer.cert.tbs_certificate.subject.clone(),
+            this_update: now,
+            next_update: Some(next_update),
+            revoked_certificates: Some(
+                revoked
+                    .iter()
+                    .map(|serial_number| RevokedCert {
+                        serial_number: serial_number.clone(),
+                        revocation_date: now,
+                        crl_entry_extensions: None,
+                    })
+                    .collect(),
+            ),
+            crl_extensions: Some(vec![crl_number]),
+        };
+
+        let signature: DerSignature = signing_key.sign(&tbs_cert_list.to_der().unwrap());
+        CertificateList {
+            signature_algorithm: tbs_cert_list.signature.clone(),
+            tbs_cert_list,
+            signature: BitString::from_bytes(signature.as_bytes()).unwrap(),
+        }
+        .to_der()
+        .unwrap()
+    }
+
+    #[test]
+    fn crls() {
+        let cert_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+        let doc = sign(&cert_chain, Pcrs::default(), None);
+        let end_serial = cert_chain
+            .end_signer
+            .cert
+            .tbs_certificate
+            .serial_number
+            .clone();
+        let int_serial = cert_chain
+            .int_signer()
+            .cert
+            .tbs_certificate
+            .serial_number
+            .clone();
+
+        // Neither certificate is revoked
+        let int_crl = issue_crl(cert_chain.int_signer(), &[SerialNumber::from(1u32)]);
+        AttestationVerifier::new(root.clone())
+            .crls(vec![int_crl])
+            .verify(&doc)
+            .unwrap();
+
+        let int_crl = issue_crl(cert_chain.int_signer(), &[end_serial]);
+        let err = AttestationVerifier::new(root.clone())
+            .crls(vec![int_crl])
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::CertificateRevoked);
+
+        let root_crl = issue_crl(&cert_chain.root_signer, &[int_serial]);
+        let err = AttestationVerifier::new(root.clone())
+            .crls(vec![root_crl])
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::CertificateRevoked);
+
+        let err = AttestationVerifier::new(root)
+            .crls(vec![vec![0; 8]])
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::Crl);
+    }
+
+    #[test]
+    fn allow_skew() {
+        let minute = Duration::from_secs(60);
+
+        let not_yet_valid = NsmCertChain::builder(minute * 10)
+            .not_before(SystemTime::now() + minute)
+            .build()
+            .unwrap();
+        let doc = sign(&not_yet_valid, Pcrs::default(), None);
+        let root = not_yet_valid.root_signer.cert.to_der().unwrap();
+
+        let err = AttestationVerifier::new(root.clone())
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::CertificateNotYetValid);
+        let err = AttestationVerifier::new(root.clone())
+            .allow_skew(Duration::from_secs(1))
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::CertificateNotYetValid);
+        AttestationVerifier::new(root)
+            .allow_skew(minute * 2)
+            .verify(&doc)
+            .unwrap();
+
+        let expired = NsmCertChain::builder(minute)
+            .not_before(SystemTime::now() - minute * 2)
+            .build()
+            .unwrap();
+        let doc = sign(&expired, Pcrs::default(), None);
+        let root = expired.root_signer.cert.to_der().unwrap();
+
+        let err = AttestationVerifier::new(root.clone())
+            .allow_skew(Duration::from_secs(1))
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::CertificateExpired);
+        AttestationVerifier::new(root)
+            .allow_skew(minute * 2)
+            .verify(&doc)
+            .unwrap();
+    }
+
+    #[test]
+    fn allow_skew_is_bounded() {
+        let hour = Duration::from_secs(60 * 60);
+        let cert_chain = NsmCertChain::builder(hour)
+            .not_before(SystemTime::now())
+            .build()
+            .unwrap();
+        let doc = sign(&cert_chain, Pcrs::default(), None);
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+        let verify_at = |offset: Duration, expired: bool| {
+            let at = if expired { now + offset } else { now - offset };
+            let at = at.as_millis() as u64;
+            AttestationVerifier::new(root.clone())
+                .time(Time::fixed(at))
+                .allow_skew(Duration::from_secs(60 * 5))
+                .verify(&doc)
+        };
+
+        // Within the tolerance on either side of the validity window
+        verify_at(hour + Duration::from_secs(60), true).unwrap();
+        verify_at(Duration::from_secs(60), false).unwrap();
+
+        // Beyond the tolerance
+        let err = verify_at(hour + Duration::from_secs(60 * 10), true).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::CertificateExpired);
+        let err = verify_at(Duration::from_secs(60 * 10), false).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::CertificateNotYetValid);
+    }
+
+    #[test]
+    fn insecure_ignore_expiry() {
+        let expired = NsmCertChain::generate_expired();
+        let doc = sign(&expired, Pcrs::default(), None);
+        let root = expired.root_signer.cert.to_der().unwrap();
+
+        let err = AttestationVerifier::new(root.clone())
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::CertificateExpired);
+        AttestationVerifier::new(root.clone())
+            .insecure_ignore_expiry()
+            .verify(&doc)
+            .unwrap();
+
+        // A malformed intermediate isn't parsed before the chain is verified, the chain's error is reported
+        let doc = sign_doc(&expired, |doc| {
+            doc.cabundle(vec![
+                b"not a certificate".to_vec().into(),
+                expired.int_signer().cert.to_der().unwrap().into(),
+            ])
+        });
+        for verifier in [
+            AttestationVerifier::new(root.clone()),
+            AttestationVerifier::new(root.clone()).insecure_ignore_expiry(),
+        ] {
+            let err = verifier.verify(&doc).unwrap_err();
+            assert_eq!(err.kind(), &ErrorKind::CertificateExpired);
+        }
+
+        // The chain's structure is still verified
+        let doc = sign(&expired, Pcrs::default(), None);
+        let other_root = NsmCertChain::generate_expired()
+            .root_signer
+            .cert
+            .to_der()
+            .unwrap();
+        let err = AttestationVerifier::new(other_root)
+            .insecure_ignore_expiry()
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::BadSignature);
+    }
+
+    #[test]
+    fn key_usage() {
+        // id-kp-serverAuth and id-kp-clientAuth, DER encoded without the tag and length
+        const SERVER_AUTH: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x01];
+        const CLIENT_AUTH: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x02];
+
+        let cert_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+        let doc = sign(&cert_chain, Pcrs::default(), None);
+
+        // The end certificate doesn't include an extended key usage extension
+        AttestationVerifier::new(root.clone())
+            .key_usage(KeyUsage::required_if_present(CLIENT_AUTH))
+            .verify(&doc)
+            .unwrap();
+
+        let err = AttestationVerifier::new(root)
+            .key_usage(KeyUsage::required(SERVER_AUTH))
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::KeyUsage);
+    }
+
+    #[test]
+    fn validity() {
+        let cert_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+        let doc = sign(&cert_chain, Pcrs::default(), None);
+
+        let verified = AttestationVerifier::new(root)
+            .verify_detailed(&doc)
+            .unwrap();
+        let end_validity = &cert_chain.end_signer.cert.tbs_certificate.validity;
+        assert_eq!(
+            verified.leaf_validity,
+            Validity {
+                not_before: end_validity.not_before.to_unix_duration(),
+                not_after: end_validity.not_after.to_unix_duration(),
+            }
+        );
+
+        let int_validity = &cert_chain.int_signer().cert.tbs_certificate.validity;
+        assert_eq!(
+            verified.chain_validity.not_after,
+            end_validity
+                .not_after
+                .to_unix_duration()
+                .min(int_validity.not_after.to_unix_duration())
+        );
+        assert!(verified.chain_validity.not_before <= verified.chain_validity.not_after);
+    }
+
+    #[test]
+    fn timestamp_within_validity() {
+        let cert_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+        let sign_at = |timestamp: u64| sign_doc(&cert_chain, |doc| doc.timestamp(timestamp));
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+        AttestationVerifier::new(root.clone())
+            .timestamp_within_validity()
+            .verify(&sign_at(now.as_millis() as u64))
+            .unwrap();
+
+        // Not checked unless enabled
+        let doc = sign_at(0);
+        AttestationVerifier::new(root.clone()).verify(&doc).unwrap();
+        let err = AttestationVerifier::new(root.clone())
+            .timestamp_within_validity()
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::Timestamp);
+
+        let later = now + Duration::from_secs(60 * 60);
+        let err = AttestationVerifier::new(root)
+            .timestamp_within_validity()
+            .verify(&sign_at(later.as_millis() as u64))
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::Timestamp);
+    }
+
+    #[test]
+    fn module_id() {
+        let cert_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+        let doc = sign_doc(&cert_chain, |doc| {
+            doc.module_id("i-0123-enc4567".to_string())
+        });
+
+        let verified = AttestationVerifier::new(root.clone())
+            .module_id("i-0123-enc4567".to_string())
+            .verify_detailed(&doc)
+            .unwrap();
+        assert_eq!(verified.module_id(), "i-0123-enc4567");
+        AttestationVerifier::new(root.clone())
+            .module_id_prefix("i-0123".to_string())
+            .verify(&doc)
+            .unwrap();
+
+        for verifier in [
+            AttestationVerifier::new(root.clone()).module_id("i-0123".to_string()),
+            AttestationVerifier::new(root).module_id_prefix("i-4567".to_string()),
+        ] {
+            let err = verifier.verify(&doc).unwrap_err();
+            assert_eq!(err.kind(), &ErrorKind::ModuleId);
+        }
+    }
+
+    #[test]
+    fn digest() {
+        let cert_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+
+        let verified = AttestationVerifier::new(root.clone())
+            .verify_detailed(&sign(&cert_chain, Pcrs::zeros(), None))
+            .unwrap();
+        assert_eq!(verified.digest(), Digest::SHA384);
+
+        // SHA256 PCRs still produce a SHA384 `digest`, matching the ES384 signature
+        let verified = AttestationVerifier::new(root.clone())
+            .verify_detailed(&sign(
+                &cert_chain,
+                Pcrs::zeros_with_digest(PcrDigest::Sha256),
+                None,
+            ))
+            .unwrap();
+        assert_eq!(verified.digest(), Digest::SHA384);
+
+        // Signed with ES384, but claiming SHA256
+        let doc = sign_doc(&cert_chain, |doc| doc.digest(Digest::SHA256));
+        let err = AttestationVerifier::new(root).verify(&doc).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::Digest);
+    }
+
+    #[test]
+    fn max_document_size() {
+        let cert_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+        let doc = sign(&cert_chain, Pcrs::default(), None);
+
+        AttestationVerifier::new(root.clone())
+            .max_document_size(doc.len())
+            .verify(&doc)
+            .unwrap();
+        let err = AttestationVerifier::new(root.clone())
+            .max_document_size(doc.len() - 1)
+            .verify(&doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::DocumentSize);
+
+        // Rejected before it's parsed
+        let err = AttestationVerifier::new(root)
+            .verify(&vec![0; super::MAX_DOCUMENT_SIZE + 1])
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::DocumentSize);
+    }
+
+    #[test]
+    fn leaf() {
+        let cert_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+        let doc = sign(&cert_chain, Pcrs::default(), None);
+
+        let verified = AttestationVerifier::new(root)
+            .verify_detailed(&doc)
+            .unwrap();
+        assert_eq!(verified.leaf, cert_chain.end_signer.cert);
+    }
+
+    #[test]
+    fn protected_algorithm() {
+        let cert_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+        let doc = sign(&cert_chain, Pcrs::default(), None);
+
+        let verified = AttestationVerifier::new(root.clone())
+            .verify_detailed(&doc)
+            .unwrap();
+        assert_eq!(
+            verified.protected_algorithm(),
+            Some(&coset::Algorithm::Assigned(iana::Algorithm::ES384))
+        );
+        assert_eq!(verified.protected_key_id(), None);
+
+        // Re-sign the same payload with a header claiming a different algorithm
+        let signing_key = end_signing_key(&cert_chain);
+        let payload = CoseSign1::from_slice(&doc).unwrap().payload.unwrap();
+        let downgraded = CoseSign1Builder::new()
+            .payload(payload)
+            .protected(
+                HeaderBuilder::new()
+                    .algorithm(iana::Algorithm::ES256)
+                    .build(),
+            )
+            .create_signature(b"", |bytes| {
+                let signature: Signature = signing_key.sign(bytes);
+                signature.to_bytes().to_vec()
+            })
+            .build()
+            .to_vec()
+            .unwrap();
+        let err = AttestationVerifier::new(root)
+            .verify(&downgraded)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::Cose);
+    }
+
+    #[test]
+    fn deny_debug_mode() {
+        let cert_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+
+        let debug_doc = sign(&cert_chain, Pcrs::zeros(), None);
+        AttestationVerifier::new(root.clone())
+            .verify(&debug_doc)
+            .unwrap();
+        let err = AttestationVerifier::new(root.clone())
+            .deny_debug_mode()
+            .verify(&debug_doc)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::DebugMode);
+
+        let mut pcrs = Pcrs::zeros();
+        pcrs.set(PcrIndex::Zero, Pcr::from([1; 48])).unwrap();
+        let doc = sign(&cert_chain, pcrs, None);
+        AttestationVerifier::new(root)
+            .deny_debug_mode()
+            .verify(&doc)
+            .unwrap();
+    }
+
+    #[test]
+    fn send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<AttestationVerifier>();
+        assert_send_sync::<super::VerifiedAttestationDoc>();
+        assert_send_sync::<crate::verify::VerifyError>();
+    }
+
+    #[test]
+    fn protected_key_id() {
+        let cert_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+        let doc = doc_builder(&cert_chain)
+            .build()
+            .sign_with_kid(end_signing_key(&cert_chain), b"dev-key")
+            .unwrap();
+
+        let verified = AttestationVerifier::new(root)
+            .verify_detailed(&doc)
+            .unwrap();
+        assert_eq!(verified.protected_key_id(), Some(b"dev-key".as_slice()));
+    }
+
+    #[test]
+    fn tampered_signature() {
+        let cert_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+        let mut cose = doc_builder(&cert_chain)
+            .build()
+            .sign_to_cose(end_signing_key(&cert_chain))
+            .unwrap();
+
+        let verifier = AttestationVerifier::new(root);
+        assert!(verifier.verify(&cose.clone().to_vec().unwrap()).is_ok());
+
+        cose.signature[0] ^= 0xff;
+        let err = verifier.verify(&cose.to_vec().unwrap()).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::Verification);
+    }
+
+    #[test]
+    fn signing_algorithms() {
+        use crate::driver::dev::CoseSigningKey;
+        use nsm_nitro_enclave_utils_keygen::KeyAlgorithm;
+        use p384::ecdsa::signature::Verifier;
+
+        let p384_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let p256_chain = NsmCertChain::builder(Duration::from_secs(60))
+            .key_algorithm(KeyAlgorithm::P256)
+            .build()
+            .unwrap();
+
+        // Every algorithm signs, but only ES384 verifies: authentic attestation documents are always signed with it
+        let cases: [(
+            &NsmCertChain,
+            CoseSigningKey,
+            iana::Algorithm,
+            Option<ErrorKind>,
+        ); 3] = [
+            (
+                &p256_chain,
+                p256_chain
+                    .end_signer
+                    .signing_key
+                    .as_p256()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                iana::Algorithm::ES256,
+                Some(ErrorKind::Cose),
+            ),
+            (
+                &p384_chain,
+                p384_chain
+                    .end_signer
+                    .signing_key
+                    .as_p384()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                iana::Algorithm::ES384,
+                None,
+            ),
+            (
+                &p384_chain,
+                p521::ecdsa::SigningKey::random(&mut rand::thread_rng()).into(),
+                iana::Algorithm::ES512,
+                Some(ErrorKind::Cose),
+            ),
+        ];
+        for (cert_chain, signing_key, alg, expected) in cases {
+            let cose = doc_builder(cert_chain)
+                .build()
+                .sign_to_cose(signing_key.clone())
+                .unwrap();
+
+            // The protected header matches the curve, and the signature is valid for it
+            assert_eq!(
+                cose.protected.header.alg,
+                Some(coset::Algorithm::Assigned(alg))
+            );
+            cose.verify_signature(b"", |signature, msg| match &signing_key {
+                CoseSigningKey::ES256(key) => key
+                    .verifying_key()
+                    .verify(msg, &p256::ecdsa::Signature::try_from(signature)?),
+                CoseSigningKey::ES384(key) => key
+                    .verifying_key()
+                    .verify(msg, &Signature::try_from(signature)?),
+                CoseSigningKey::ES512(key) => p521::ecdsa::VerifyingKey::from(key)
+                    .verify(msg, &p521::ecdsa::Signature::try_from(signature)?),
+            })
+            .unwrap();
+
+            let root = cert_chain.root_signer.cert.to_der().unwrap();
+            let result = AttestationVerifier::new(root).verify(&cose.to_vec().unwrap());
+            assert_eq!(result.err().map(|err| *err.kind()), expected);
+        }
+    }
+
+    #[test]
+    fn enclave_public_key() {
+        use p384::pkcs8::EncodePublicKey;
+
+        let cert_chain = NsmCertChain::generate(Duration::from_secs(60));
+        let root = cert_chain.root_signer.cert.to_der().unwrap();
+        let enclave_key = p384::ecdsa::SigningKey::random(&mut rand::thread_rng());
+        let enclave_key = enclave_key.verifying_key();
+        let verify = |public_key: Option<Vec<u8>>| {
+            let doc = sign_doc(&cert_chain, |doc| match public_key {
+                Some(public_key) => doc.public_key(public_key.into()),
+                None => doc,
+            });
+            AttestationVerifier::new(root.clone())
+                .verify_detailed(&doc)
+                .unwrap()
+        };
+
+        let verified = verify(None);
+        assert_eq!(verified.enclave_public_key(), None);
+        assert_eq!(verified.enclave_verifying_key().unwrap(), None);
+
+        let sec1 = enclave_key.to_encoded_point(true).as_bytes().to_vec();
+        let spki = enclave_key.to_public_key_der().unwrap().into_vec();
+        for public_key in [sec1, spki] {
+            let verified = verify(Some(public_key.clone()));
+            assert_eq!(verified.enclave_public_key(), Some(public_key.as_slice()));
+            assert_eq!(
+                verified.enclave_verifying_key().unwrap().as_ref(),
+                Some(enclave_key)
+            );
+        }
+
+        let err = verify(Some(b"not a key".to_vec()))
+            .enclave_verifying_key()
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::AttestationDoc);
+    }
+}